@@ -1,4 +1,7 @@
-use dive_deco::{BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel};
+use dive_deco::{
+    BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel, Depth, GfLowReference, StopRounding,
+    Time,
+};
 
 fn main() {
     // model with default config (GF 100/100)
@@ -9,11 +12,30 @@ fn main() {
     // model with full config instance
     let config_instance = BuehlmannConfig {
         gf: (85, 85),
+        per_compartment_gf: None,
+        gf_low_reference: GfLowReference::MaxSupersaturation,
         surface_pressure: 1013,
         deco_ascent_rate: 9.,
         ceiling_type: CeilingType::Actual,
         round_ceiling: false,
         recalc_all_tissues_m_values: true,
+        safety_stop: None,
+        water_vapor_pressure: 0.0627,
+        respiratory_quotient: None,
+        gas_density_limit: None,
+        deco_pp_o2_limit: 1.6,
+        bottom_pp_o2_limit: 1.4,
+        first_stop_rounding: StopRounding::Up,
+        cns_limit_over_max_pp_o2: Time::from_seconds(400.),
+        oxtox_during_travel: true,
+        switch_at_stops_only: false,
+        prefer_he_washout: false,
+        o2_narcotic: true,
+        min_stop_time: Time::zero(),
+        ceiling_hysteresis: Depth::zero(),
+        oxygen_window_stop: Time::zero(),
+        deco_stop_resolution: Time::from_seconds(1.),
+        max_depth: Depth::from_meters(350.),
     };
     let model_2 = BuehlmannModel::new(config_instance);
     println!("{:?}", model_2.config());
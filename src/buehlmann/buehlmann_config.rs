@@ -1,23 +1,59 @@
+use crate::common::{ALVEOLI_WATER_VAPOR_PRESSURE, CNS_LIMIT_OVER_MAX_PP02_SECONDS};
 use crate::{
     common::{
-        AscentRatePerMinute, ConfigValidationErr, DecoModelConfig, GradientFactors, MbarPressure,
+        AscentRate, AscentRatePerMinute, ConfigValidationErr, Conservatism, DecoModelConfig,
+        GfLowReference, GradientFactor, GradientFactors, MbarPressure, SurfacePressure,
     },
-    CeilingType,
+    CeilingType, Depth, Pressure, SafetyStop, StopRounding, Time,
 };
 
 const GF_RANGE_ERR_MSG: &str = "GF values have to be in 1-100 range";
 const GF_ORDER_ERR_MSG: &str = "GFLow can't be higher than GFHigh";
 const SURFACE_PRESSURE_ERR_MSG: &str = "Surface pressure must be in milibars in 500-1500 range";
 const DECO_ASCENT_RATE_ERR_MSG: &str = "Ascent rate must in 1-30 m/s range";
+const WATER_VAPOR_PRESSURE_ERR_MSG: &str = "Water vapor pressure must be in 0-0.1 bar range";
+const GAS_DENSITY_LIMIT_ERR_MSG: &str = "Gas density limit must be a positive value";
+const RESPIRATORY_QUOTIENT_ERR_MSG: &str = "Respiratory quotient must be in 0.5-1.5 range";
+const PP_O2_LIMIT_ERR_MSG: &str = "ppO2 limit must be in 0.5-2.0 bar range";
+const CNS_LIMIT_ERR_MSG: &str = "CNS time limit over max ppO2 must be positive";
+const MIN_STOP_TIME_ERR_MSG: &str = "Min stop time must be a non-negative value";
+const OXYGEN_WINDOW_STOP_ERR_MSG: &str = "Oxygen window stop must be a non-negative value";
+const MAX_DEPTH_ERR_MSG: &str = "Max depth must be a positive value";
+const DECO_STOP_RESOLUTION_ERR_MSG: &str =
+    "Deco stop resolution must be in 0-60s range (exclusive of 0)";
+// max deco stop evaluation interval
+const MAX_DECO_STOP_RESOLUTION_SECONDS: f64 = 60.;
+const CEILING_HYSTERESIS_ERR_MSG: &str = "Ceiling hysteresis must be in 0-3m range";
+const SAFETY_STOP_ERR_MSG: &str = "Safety stop depth and time must be positive";
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BuehlmannConfig {
     pub gf: GradientFactors,
+    pub per_compartment_gf: Option<[GradientFactors; 16]>,
+    pub gf_low_reference: GfLowReference,
     pub surface_pressure: MbarPressure,
     pub deco_ascent_rate: AscentRatePerMinute,
     pub ceiling_type: CeilingType,
     pub round_ceiling: bool,
     pub recalc_all_tissues_m_values: bool,
+    pub safety_stop: Option<SafetyStop>,
+    pub water_vapor_pressure: Pressure,
+    pub respiratory_quotient: Option<f64>,
+    pub gas_density_limit: Option<f64>,
+    pub deco_pp_o2_limit: Pressure,
+    pub bottom_pp_o2_limit: Pressure,
+    pub first_stop_rounding: StopRounding,
+    pub cns_limit_over_max_pp_o2: Time,
+    pub oxtox_during_travel: bool,
+    pub switch_at_stops_only: bool,
+    pub prefer_he_washout: bool,
+    pub o2_narcotic: bool,
+    pub min_stop_time: Time,
+    pub ceiling_hysteresis: Depth,
+    pub oxygen_window_stop: Time,
+    pub deco_stop_resolution: Time,
+    pub max_depth: Depth,
 }
 
 impl BuehlmannConfig {
@@ -30,13 +66,55 @@ impl BuehlmannConfig {
         self
     }
 
-    pub fn with_surface_pressure(mut self, surface_pressure: MbarPressure) -> Self {
-        self.surface_pressure = surface_pressure;
+    /// single gradient factor as both GF low and GF high (e.g. recreational GF 85/85): no GF slope,
+    /// same supersaturation limit at every depth and no GF low depth determination
+    pub fn with_single_gf(mut self, gf: GradientFactor) -> Self {
+        self.gf = (gf, gf);
         self
     }
 
-    pub fn with_deco_ascent_rate(mut self, deco_ascent_rate: AscentRatePerMinute) -> Self {
-        self.deco_ascent_rate = deco_ascent_rate;
+    /// compartment-specific gradient factors (ordered by compartment number), overriding
+    /// the global pair (e.g. more conservative fast compartments), default: none.
+    /// Compartments over 16 in custom compartments sets use the global pair
+    pub fn with_per_compartment_gf(
+        mut self,
+        per_compartment_gf: Option<[GradientFactors; 16]>,
+    ) -> Self {
+        self.per_compartment_gf = per_compartment_gf;
+        self
+    }
+
+    /// gradient factors of compartment, global pair if no compartment-specific gradient factors
+    /// (or compartment number out of compartment-specific gradient factors range)
+    pub fn compartment_gf(&self, compartment_no: u8) -> GradientFactors {
+        self.per_compartment_gf
+            .and_then(|per_compartment_gf| {
+                let index = compartment_no.checked_sub(1)? as usize;
+                per_compartment_gf.get(index).copied()
+            })
+            .unwrap_or(self.gf)
+    }
+
+    /// depth GF low applies at: max supersaturation depth (default) or first deco stop
+    pub fn with_gf_low_reference(mut self, gf_low_reference: GfLowReference) -> Self {
+        self.gf_low_reference = gf_low_reference;
+        self
+    }
+
+    /// gradient factors preset, overridable by subsequent with_gradient_factors
+    pub fn with_conservatism(self, conservatism: Conservatism) -> Self {
+        let (gf_low, gf_high) = conservatism.gradient_factors();
+        self.with_gradient_factors(gf_low, gf_high)
+    }
+
+    pub fn with_surface_pressure(mut self, surface_pressure: impl Into<SurfacePressure>) -> Self {
+        self.surface_pressure = surface_pressure.into().as_mbar();
+        self
+    }
+
+    /// deco ascent rate, m/min or `AscentRate` (e.g. `AscentRate::from_ft_per_min(30.)`)
+    pub fn with_deco_ascent_rate(mut self, deco_ascent_rate: impl Into<AscentRate>) -> Self {
+        self.deco_ascent_rate = deco_ascent_rate.into().as_m_per_min();
         self
     }
 
@@ -54,17 +132,130 @@ impl BuehlmannConfig {
         self.recalc_all_tissues_m_values = recalc_all_tissues_m_values;
         self
     }
+
+    pub fn with_water_vapor_pressure(mut self, water_vapor_pressure: Pressure) -> Self {
+        self.water_vapor_pressure = water_vapor_pressure;
+        self
+    }
+
+    pub fn with_respiratory_quotient(mut self, respiratory_quotient: f64) -> Self {
+        self.respiratory_quotient = Some(respiratory_quotient);
+        self
+    }
+
+    pub fn with_gas_density_limit(mut self, gas_density_limit: f64) -> Self {
+        self.gas_density_limit = Some(gas_density_limit);
+        self
+    }
+
+    pub fn with_deco_pp_o2_limit(mut self, deco_pp_o2_limit: Pressure) -> Self {
+        self.deco_pp_o2_limit = deco_pp_o2_limit;
+        self
+    }
+
+    pub fn with_bottom_pp_o2_limit(mut self, bottom_pp_o2_limit: Pressure) -> Self {
+        self.bottom_pp_o2_limit = bottom_pp_o2_limit;
+        self
+    }
+
+    pub fn with_first_stop_rounding(mut self, first_stop_rounding: StopRounding) -> Self {
+        self.first_stop_rounding = first_stop_rounding;
+        self
+    }
+
+    pub fn with_cns_limit_over_max_pp_o2(mut self, cns_limit_over_max_pp_o2: Time) -> Self {
+        self.cns_limit_over_max_pp_o2 = cns_limit_over_max_pp_o2;
+        self
+    }
+
+    /// accrue oxygen toxicity (CNS, OTU) during travel records (default: true), disabled e.g. to
+    /// match a dive computer only sampling oxygen exposure at constant depth records
+    pub fn with_oxtox_during_travel(mut self, oxtox_during_travel: bool) -> Self {
+        self.oxtox_during_travel = oxtox_during_travel;
+        self
+    }
+
+    pub fn with_switch_at_stops_only(mut self, switch_at_stops_only: bool) -> Self {
+        self.switch_at_stops_only = switch_at_stops_only;
+        self
+    }
+
+    /// prefer lower He deco gas among switch candidates with similar O2 content (He washout)
+    pub fn with_prefer_he_washout(mut self, prefer_he_washout: bool) -> Self {
+        self.prefer_he_washout = prefer_he_washout;
+        self
+    }
+
+    pub fn with_o2_narcotic(mut self, o2_narcotic: bool) -> Self {
+        self.o2_narcotic = o2_narcotic;
+        self
+    }
+
+    pub fn with_min_stop_time(mut self, min_stop_time: Time) -> Self {
+        self.min_stop_time = min_stop_time;
+        self
+    }
+
+    /// stop at gas switch depth after each deco gas switch (default: none)
+    pub fn with_oxygen_window_stop(mut self, oxygen_window_stop: Time) -> Self {
+        self.oxygen_window_stop = oxygen_window_stop;
+        self
+    }
+
+    /// interval deco stops are simulated and re-evaluated in (default: 1s), final interval of each
+    /// stop refined to 1s. Coarser resolution means fewer ceiling evaluations (faster planning),
+    /// stop times may differ from 1s resolution by float rounding of segmented saturation
+    /// (up to a few seconds on long deco)
+    pub fn with_deco_stop_resolution(mut self, deco_stop_resolution: Time) -> Self {
+        self.deco_stop_resolution = deco_stop_resolution;
+        self
+    }
+
+    /// max depth of model validity, deeper records rejected
+    pub fn with_max_depth(mut self, max_depth: Depth) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_ceiling_hysteresis(mut self, ceiling_hysteresis: Depth) -> Self {
+        self.ceiling_hysteresis = ceiling_hysteresis;
+        self
+    }
+
+    pub fn with_safety_stop(mut self, safety_stop: SafetyStop) -> Self {
+        self.safety_stop = Some(safety_stop);
+        self
+    }
 }
 
 impl Default for BuehlmannConfig {
     fn default() -> Self {
         Self {
             gf: (100, 100),
+            per_compartment_gf: None,
+            gf_low_reference: GfLowReference::MaxSupersaturation,
             surface_pressure: 1013,
             deco_ascent_rate: 10.,
             ceiling_type: CeilingType::Actual,
             round_ceiling: false,
             recalc_all_tissues_m_values: true,
+            safety_stop: None,
+            water_vapor_pressure: ALVEOLI_WATER_VAPOR_PRESSURE,
+            respiratory_quotient: None,
+            gas_density_limit: None,
+            deco_pp_o2_limit: 1.6,
+            bottom_pp_o2_limit: 1.4,
+            first_stop_rounding: StopRounding::Up,
+            cns_limit_over_max_pp_o2: Time::from_seconds(CNS_LIMIT_OVER_MAX_PP02_SECONDS),
+            oxtox_during_travel: true,
+            switch_at_stops_only: false,
+            prefer_he_washout: false,
+            o2_narcotic: true,
+            min_stop_time: Time::zero(),
+            ceiling_hysteresis: Depth::zero(),
+            oxygen_window_stop: Time::zero(),
+            deco_stop_resolution: Time::from_seconds(1.),
+            max_depth: Depth::from_meters(350.),
         }
     }
 }
@@ -73,14 +264,44 @@ impl DecoModelConfig for BuehlmannConfig {
     fn validate(&self) -> Result<(), ConfigValidationErr> {
         let Self {
             gf,
+            per_compartment_gf,
             surface_pressure,
             deco_ascent_rate,
+            safety_stop,
+            water_vapor_pressure,
+            respiratory_quotient,
+            gas_density_limit,
+            deco_pp_o2_limit,
+            bottom_pp_o2_limit,
+            cns_limit_over_max_pp_o2,
+            min_stop_time,
+            ceiling_hysteresis,
+            oxygen_window_stop,
+            deco_stop_resolution,
+            max_depth,
             ..
         } = self;
 
-        self.validate_gradient_factors(gf)?;
+        self.validate_gradient_factors("gf", gf)?;
+        if let Some(per_compartment_gf) = per_compartment_gf {
+            for compartment_gf in per_compartment_gf.iter() {
+                self.validate_gradient_factors("per_compartment_gf", compartment_gf)?;
+            }
+        }
         self.validate_surface_pressure(surface_pressure)?;
         self.validate_deco_ascent_rate(deco_ascent_rate)?;
+        self.validate_safety_stop(safety_stop)?;
+        self.validate_water_vapor_pressure(water_vapor_pressure)?;
+        self.validate_respiratory_quotient(respiratory_quotient)?;
+        self.validate_gas_density_limit(gas_density_limit)?;
+        self.validate_pp_o2_limit("deco_pp_o2_limit", deco_pp_o2_limit)?;
+        self.validate_pp_o2_limit("bottom_pp_o2_limit", bottom_pp_o2_limit)?;
+        self.validate_cns_limit(cns_limit_over_max_pp_o2)?;
+        self.validate_min_stop_time(min_stop_time)?;
+        self.validate_ceiling_hysteresis(ceiling_hysteresis)?;
+        self.validate_oxygen_window_stop(oxygen_window_stop)?;
+        self.validate_deco_stop_resolution(deco_stop_resolution)?;
+        self.validate_max_depth(max_depth)?;
 
         Ok(())
     }
@@ -100,19 +321,67 @@ impl DecoModelConfig for BuehlmannConfig {
     fn round_ceiling(&self) -> bool {
         self.round_ceiling
     }
+
+    fn deco_pp_o2_limit(&self) -> Pressure {
+        self.deco_pp_o2_limit
+    }
+
+    fn bottom_pp_o2_limit(&self) -> Pressure {
+        self.bottom_pp_o2_limit
+    }
+
+    fn gas_density_limit(&self) -> Option<f64> {
+        self.gas_density_limit
+    }
+
+    fn first_stop_rounding(&self) -> StopRounding {
+        self.first_stop_rounding
+    }
+
+    fn switch_at_stops_only(&self) -> bool {
+        self.switch_at_stops_only
+    }
+
+    fn prefer_he_washout(&self) -> bool {
+        self.prefer_he_washout
+    }
+
+    fn o2_narcotic(&self) -> bool {
+        self.o2_narcotic
+    }
+
+    fn min_stop_time(&self) -> Time {
+        self.min_stop_time
+    }
+
+    fn oxygen_window_stop(&self) -> Time {
+        self.oxygen_window_stop
+    }
+
+    fn safety_stop(&self) -> Option<SafetyStop> {
+        self.safety_stop
+    }
+
+    fn deco_stop_resolution(&self) -> Time {
+        self.deco_stop_resolution
+    }
 }
 
 impl BuehlmannConfig {
-    fn validate_gradient_factors(&self, gf: &GradientFactors) -> Result<(), ConfigValidationErr> {
+    fn validate_gradient_factors(
+        &self,
+        field: &str,
+        gf: &GradientFactors,
+    ) -> Result<(), ConfigValidationErr> {
         let (gf_low, gf_high) = gf;
         let gf_range = 1..=100;
 
         if !gf_range.contains(gf_low) || !gf_range.contains(gf_high) {
-            return Err(ConfigValidationErr::new("gf", GF_RANGE_ERR_MSG));
+            return Err(ConfigValidationErr::new(field, GF_RANGE_ERR_MSG));
         }
 
         if gf_low > gf_high {
-            return Err(ConfigValidationErr::new("gf", GF_ORDER_ERR_MSG));
+            return Err(ConfigValidationErr::new(field, GF_ORDER_ERR_MSG));
         }
 
         Ok(())
@@ -138,7 +407,7 @@ impl BuehlmannConfig {
         deco_ascent_rate: &AscentRatePerMinute,
     ) -> Result<(), ConfigValidationErr> {
         let ascent_rate_range = 1.0..=30.0;
-        if !ascent_rate_range.contains(deco_ascent_rate) {
+        if !deco_ascent_rate.is_finite() || !ascent_rate_range.contains(deco_ascent_rate) {
             return Err(ConfigValidationErr::new(
                 "deco_ascent_rate",
                 DECO_ASCENT_RATE_ERR_MSG,
@@ -147,6 +416,161 @@ impl BuehlmannConfig {
 
         Ok(())
     }
+
+    fn validate_water_vapor_pressure(
+        &self,
+        water_vapor_pressure: &Pressure,
+    ) -> Result<(), ConfigValidationErr> {
+        let water_vapor_pressure_range = 0.0..=0.1;
+        if !water_vapor_pressure_range.contains(water_vapor_pressure) {
+            return Err(ConfigValidationErr::new(
+                "water_vapor_pressure",
+                WATER_VAPOR_PRESSURE_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_respiratory_quotient(
+        &self,
+        respiratory_quotient: &Option<f64>,
+    ) -> Result<(), ConfigValidationErr> {
+        if let Some(respiratory_quotient) = respiratory_quotient {
+            let respiratory_quotient_range = 0.5..=1.5;
+            if !respiratory_quotient_range.contains(respiratory_quotient) {
+                return Err(ConfigValidationErr::new(
+                    "respiratory_quotient",
+                    RESPIRATORY_QUOTIENT_ERR_MSG,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_gas_density_limit(
+        &self,
+        gas_density_limit: &Option<f64>,
+    ) -> Result<(), ConfigValidationErr> {
+        if let Some(gas_density_limit) = gas_density_limit {
+            if !gas_density_limit.is_finite() || *gas_density_limit <= 0. {
+                return Err(ConfigValidationErr::new(
+                    "gas_density_limit",
+                    GAS_DENSITY_LIMIT_ERR_MSG,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_pp_o2_limit(
+        &self,
+        field: &str,
+        pp_o2_limit: &Pressure,
+    ) -> Result<(), ConfigValidationErr> {
+        let pp_o2_limit_range = 0.5..=2.0;
+        if !pp_o2_limit_range.contains(pp_o2_limit) {
+            return Err(ConfigValidationErr::new(field, PP_O2_LIMIT_ERR_MSG));
+        }
+
+        Ok(())
+    }
+
+    fn validate_cns_limit(&self, cns_limit: &Time) -> Result<(), ConfigValidationErr> {
+        if !cns_limit.as_seconds().is_finite() || *cns_limit <= Time::zero() {
+            return Err(ConfigValidationErr::new(
+                "cns_limit_over_max_pp_o2",
+                CNS_LIMIT_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_min_stop_time(&self, min_stop_time: &Time) -> Result<(), ConfigValidationErr> {
+        if !min_stop_time.as_seconds().is_finite() || *min_stop_time < Time::zero() {
+            return Err(ConfigValidationErr::new(
+                "min_stop_time",
+                MIN_STOP_TIME_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_oxygen_window_stop(
+        &self,
+        oxygen_window_stop: &Time,
+    ) -> Result<(), ConfigValidationErr> {
+        if !oxygen_window_stop.as_seconds().is_finite() || *oxygen_window_stop < Time::zero() {
+            return Err(ConfigValidationErr::new(
+                "oxygen_window_stop",
+                OXYGEN_WINDOW_STOP_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_deco_stop_resolution(
+        &self,
+        deco_stop_resolution: &Time,
+    ) -> Result<(), ConfigValidationErr> {
+        let resolution_seconds = deco_stop_resolution.as_seconds();
+        if resolution_seconds.is_nan()
+            || resolution_seconds <= 0.
+            || resolution_seconds > MAX_DECO_STOP_RESOLUTION_SECONDS
+        {
+            return Err(ConfigValidationErr::new(
+                "deco_stop_resolution",
+                DECO_STOP_RESOLUTION_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_max_depth(&self, max_depth: &Depth) -> Result<(), ConfigValidationErr> {
+        if !max_depth.as_meters().is_finite() || *max_depth <= Depth::zero() {
+            return Err(ConfigValidationErr::new("max_depth", MAX_DEPTH_ERR_MSG));
+        }
+
+        Ok(())
+    }
+
+    fn validate_ceiling_hysteresis(
+        &self,
+        ceiling_hysteresis: &Depth,
+    ) -> Result<(), ConfigValidationErr> {
+        let ceiling_hysteresis_range = 0.0..=3.0;
+        if !ceiling_hysteresis_range.contains(&ceiling_hysteresis.as_meters()) {
+            return Err(ConfigValidationErr::new(
+                "ceiling_hysteresis",
+                CEILING_HYSTERESIS_ERR_MSG,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_safety_stop(
+        &self,
+        safety_stop: &Option<SafetyStop>,
+    ) -> Result<(), ConfigValidationErr> {
+        if let Some(SafetyStop { depth, time, .. }) = safety_stop {
+            if !depth.as_meters().is_finite()
+                || !time.as_seconds().is_finite()
+                || *depth <= Depth::zero()
+                || *time <= Time::zero()
+            {
+                return Err(ConfigValidationErr::new("safety_stop", SAFETY_STOP_ERR_MSG));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -170,6 +594,35 @@ mod tests {
         assert_eq!(config.gf, (30, 70));
     }
 
+    #[test]
+    fn test_conservatism_presets() {
+        let presets = [
+            (Conservatism::Low, (40, 85)),
+            (Conservatism::Medium, (35, 75)),
+            (Conservatism::High, (30, 70)),
+        ];
+        for (conservatism, expected_gf) in presets {
+            let config = BuehlmannConfig::new().with_conservatism(conservatism);
+            assert_eq!(config.validate(), Ok(()));
+            assert_eq!(config.gf, expected_gf);
+        }
+    }
+
+    #[test]
+    fn test_conservatism_overridden_by_gradient_factors() {
+        let config = BuehlmannConfig::new()
+            .with_conservatism(Conservatism::High)
+            .with_gradient_factors(50, 90);
+        assert_eq!(config.gf, (50, 90));
+    }
+
+    #[test]
+    fn test_single_gf() {
+        let config = BuehlmannConfig::new().with_single_gf(85);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.gf, (85, 85));
+    }
+
     #[test]
     fn test_gf_range() {
         let invalid_gf_range_cases = vec![(1, 101), (0, 99), (120, 240)];
@@ -197,6 +650,9 @@ mod tests {
         let config = BuehlmannConfig::new().with_surface_pressure(1032);
         assert_eq!(config.validate(), Ok(()));
         assert_eq!(config.surface_pressure, 1032);
+        let config = BuehlmannConfig::new().with_surface_pressure(SurfacePressure::from_bar(0.85));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.surface_pressure, 850);
     }
 
     #[test]
@@ -221,6 +677,265 @@ mod tests {
         assert_eq!(config.deco_ascent_rate, 15.5);
     }
 
+    #[test]
+    fn test_imperial_deco_ascent_rate_config() {
+        let config = BuehlmannConfig::new().with_deco_ascent_rate(AscentRate::from_ft_per_min(30.));
+        assert_eq!(config.validate(), Ok(()));
+        assert!((config.deco_ascent_rate - 9.14).abs() < 0.01);
+        let config =
+            BuehlmannConfig::new().with_deco_ascent_rate(AscentRate::from_ft_per_min(120.));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigValidationErr::new(
+                "deco_ascent_rate",
+                DECO_ASCENT_RATE_ERR_MSG
+            ))
+        );
+    }
+
+    #[test]
+    fn test_o2_narcotic_config() {
+        assert!(BuehlmannConfig::default().o2_narcotic);
+        let config = BuehlmannConfig::new().with_o2_narcotic(false);
+        assert_eq!(config.validate(), Ok(()));
+        assert!(!config.o2_narcotic());
+    }
+
+    #[test]
+    fn test_min_stop_time_config() {
+        assert_eq!(BuehlmannConfig::default().min_stop_time, Time::zero());
+        let config = BuehlmannConfig::new().with_min_stop_time(Time::from_minutes(1.));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.min_stop_time(), Time::from_minutes(1.));
+
+        for invalid_case in [-1., f64::NAN, f64::INFINITY] {
+            let config =
+                BuehlmannConfig::new().with_min_stop_time(Time::from_seconds(invalid_case));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "min_stop_time",
+                    MIN_STOP_TIME_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_oxygen_window_stop_config() {
+        assert_eq!(BuehlmannConfig::default().oxygen_window_stop, Time::zero());
+        let config = BuehlmannConfig::new().with_oxygen_window_stop(Time::from_minutes(2.));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.oxygen_window_stop(), Time::from_minutes(2.));
+
+        for invalid_case in [-1., f64::NAN] {
+            let config =
+                BuehlmannConfig::new().with_oxygen_window_stop(Time::from_seconds(invalid_case));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "oxygen_window_stop",
+                    OXYGEN_WINDOW_STOP_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_depth_config() {
+        assert_eq!(
+            BuehlmannConfig::default().max_depth,
+            Depth::from_meters(350.)
+        );
+        let config = BuehlmannConfig::new().with_max_depth(Depth::from_meters(100.));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.max_depth, Depth::from_meters(100.));
+
+        for invalid_case in [0., -10., f64::NAN, f64::INFINITY] {
+            let config = BuehlmannConfig::new().with_max_depth(Depth::from_meters(invalid_case));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new("max_depth", MAX_DEPTH_ERR_MSG))
+            );
+        }
+    }
+
+    #[test]
+    fn test_per_compartment_gf_config() {
+        let mut per_compartment_gf = [(30, 70); 16];
+        per_compartment_gf[15] = (50, 90);
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_per_compartment_gf(Some(per_compartment_gf));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.compartment_gf(1), (30, 70));
+        assert_eq!(config.compartment_gf(16), (50, 90));
+        assert_eq!(BuehlmannConfig::new().compartment_gf(16), (100, 100));
+        // custom compartments over 16 with global pair
+        assert_eq!(config.compartment_gf(17), (30, 70));
+        assert_eq!(config.compartment_gf(32), (30, 70));
+
+        per_compartment_gf[0] = (80, 70);
+        let invalid_config = config.with_per_compartment_gf(Some(per_compartment_gf));
+        assert_eq!(
+            invalid_config.validate(),
+            Err(ConfigValidationErr::new(
+                "per_compartment_gf",
+                GF_ORDER_ERR_MSG
+            ))
+        );
+    }
+
+    #[test]
+    fn test_deco_stop_resolution_config() {
+        let config = BuehlmannConfig::new().with_deco_stop_resolution(Time::from_seconds(10.));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.deco_stop_resolution(), Time::from_seconds(10.));
+        for invalid_resolution in [0., -1., 61., f64::NAN] {
+            let config = BuehlmannConfig::new()
+                .with_deco_stop_resolution(Time::from_seconds(invalid_resolution));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "deco_stop_resolution",
+                    DECO_STOP_RESOLUTION_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ceiling_hysteresis_config() {
+        assert_eq!(BuehlmannConfig::default().ceiling_hysteresis, Depth::zero());
+        let config = BuehlmannConfig::new().with_ceiling_hysteresis(Depth::from_meters(1.));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.ceiling_hysteresis, Depth::from_meters(1.));
+
+        for invalid_case in [-1., 3.5, f64::NAN] {
+            let config =
+                BuehlmannConfig::new().with_ceiling_hysteresis(Depth::from_meters(invalid_case));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "ceiling_hysteresis",
+                    CEILING_HYSTERESIS_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_water_vapor_pressure_config() {
+        assert_eq!(
+            BuehlmannConfig::default().water_vapor_pressure,
+            ALVEOLI_WATER_VAPOR_PRESSURE
+        );
+        let config = BuehlmannConfig::new().with_water_vapor_pressure(0.0493);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.water_vapor_pressure, 0.0493);
+
+        for invalid_case in [-0.01, 0.2, f64::NAN] {
+            let config = BuehlmannConfig::new().with_water_vapor_pressure(invalid_case);
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "water_vapor_pressure",
+                    WATER_VAPOR_PRESSURE_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_respiratory_quotient_config() {
+        assert_eq!(BuehlmannConfig::default().respiratory_quotient, None);
+        let config = BuehlmannConfig::new().with_respiratory_quotient(0.8);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.respiratory_quotient, Some(0.8));
+
+        for invalid_case in [0., 2., f64::NAN] {
+            let config = BuehlmannConfig::new().with_respiratory_quotient(invalid_case);
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "respiratory_quotient",
+                    RESPIRATORY_QUOTIENT_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_gas_density_limit() {
+        for invalid_case in [0., -5.7, f64::NAN, f64::INFINITY] {
+            let config = BuehlmannConfig::new().with_gas_density_limit(invalid_case);
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "gas_density_limit",
+                    GAS_DENSITY_LIMIT_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_pp_o2_limits() {
+        let config = BuehlmannConfig::default();
+        assert_eq!(config.deco_pp_o2_limit, 1.6);
+        assert_eq!(config.bottom_pp_o2_limit, 1.4);
+
+        let config = BuehlmannConfig::new()
+            .with_deco_pp_o2_limit(1.5)
+            .with_bottom_pp_o2_limit(1.2);
+        assert_eq!(config.validate(), Ok(()));
+
+        let config = BuehlmannConfig::new().with_deco_pp_o2_limit(2.1);
+        assert_eq!(
+            config.validate(),
+            Err(ConfigValidationErr::new(
+                "deco_pp_o2_limit",
+                PP_O2_LIMIT_ERR_MSG
+            ))
+        );
+        let config = BuehlmannConfig::new().with_bottom_pp_o2_limit(0.);
+        assert_eq!(
+            config.validate(),
+            Err(ConfigValidationErr::new(
+                "bottom_pp_o2_limit",
+                PP_O2_LIMIT_ERR_MSG
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_cns_limit() {
+        for invalid_case in [0., -400., f64::NAN, f64::INFINITY] {
+            let config = BuehlmannConfig::new()
+                .with_cns_limit_over_max_pp_o2(Time::from_seconds(invalid_case));
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "cns_limit_over_max_pp_o2",
+                    CNS_LIMIT_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_safety_stop() {
+        let config = BuehlmannConfig::new().with_safety_stop(SafetyStop {
+            depth: Depth::zero(),
+            time: Time::from_minutes(3.),
+            required_above_depth: Depth::from_meters(10.),
+        });
+        assert_eq!(
+            config.validate(),
+            Err(ConfigValidationErr::new("safety_stop", SAFETY_STOP_ERR_MSG))
+        );
+    }
+
     #[test]
     fn test_invalid_deco_ascent_rate_values() {
         let invalid_deco_ascent_rate_cases = vec![-3., 0.5, 31.0, 50.5];
@@ -235,4 +950,39 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_non_finite_deco_ascent_rate() {
+        for invalid_case in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let config = BuehlmannConfig::new().with_deco_ascent_rate(invalid_case);
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new(
+                    "deco_ascent_rate",
+                    DECO_ASCENT_RATE_ERR_MSG
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_finite_safety_stop() {
+        let invalid_cases = [
+            (f64::NAN, 180.),
+            (f64::INFINITY, 180.),
+            (5., f64::NAN),
+            (5., f64::INFINITY),
+        ];
+        for (depth, time) in invalid_cases {
+            let config = BuehlmannConfig::new().with_safety_stop(SafetyStop {
+                depth: Depth::from_meters(depth),
+                time: Time::from_seconds(time),
+                required_above_depth: Depth::from_meters(10.),
+            });
+            assert_eq!(
+                config.validate(),
+                Err(ConfigValidationErr::new("safety_stop", SAFETY_STOP_ERR_MSG))
+            );
+        }
+    }
 }
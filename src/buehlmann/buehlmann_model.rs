@@ -1,44 +1,140 @@
 use crate::buehlmann::buehlmann_config::BuehlmannConfig;
-use crate::buehlmann::compartment::{Compartment, Supersaturation};
+use crate::buehlmann::compartment::{
+    Compartment, CompartmentPressures, InertLoading, Supersaturation,
+};
 use crate::buehlmann::zhl_values::{ZHLParams, ZHL_16C_N2_16A_HE_VALUES};
 use crate::common::{
-    AscentRatePerMinute, Cns, ConfigValidationErr, Deco, DecoModel, DecoModelConfig, Depth,
-    DiveState, Gas, GradientFactor, OxTox, RecordData,
+    is_valid_cns_table, AscentRatePerMinute, CNSCoeffRow, Cns, ConfigValidationErr, Deco, DecoIter,
+    DecoModel, DecoModelConfig, Depth, DiveState, Gas, GradientFactor, MbarPressure, OxTox,
+    PartialPressures, Pressure, ProfileSegment, RecordData, RecordError, CNS_COEFFICIENTS,
+    DEFAULT_CEILING_WINDOW, MIN_BREATHABLE_PP_O2,
+};
+use crate::{
+    CeilingType, DecoCalculationError, DecoRuntime, DecoSample, DecoStageType, GasSelectionReason,
+    GfLowReference, GradientFactors, Sim, Time,
 };
-use crate::{CeilingType, DecoCalculationError, DecoRuntime, GradientFactors, Sim, Time};
 use std::cmp::Ordering;
 
 const NDL_CUT_OFF_MINS: u8 = 99;
-
-#[derive(Clone, Debug)]
+// CNS clock cut-off, also returned when CNS not accruing (ppO2 up to 0.5)
+const CNS_TIME_REMAINING_CUT_OFF_MINS: f64 = 720.;
+// max number of compartments in custom compartments set
+const MAX_COMPARTMENTS: usize = 32;
+// tolerance of tissue inert gas pressure considered desaturated
+const DESATURATION_EPSILON: Pressure = 0.01;
+// aircraft cabin pressure used for no-fly time
+const CABIN_PRESSURE: MbarPressure = 750;
+// surface interval cut-off (desaturation and no-fly time)
+const SURFACE_INTERVAL_CUT_OFF_MINS: f64 = 72. * 60.;
+const CNS_TABLE_ERR_MSG: &str =
+    "CNS table ppO2 ranges must be ascending, contiguous and non-overlapping";
+const MERGED_MODELS_CONFIG_ERR_MSG: &str = "Merged models must share config and compartments set";
+// number of fastest compartments checked for isobaric counterdiffusion
+const ICD_COMPARTMENTS: usize = 5;
+// number of recreational pressure groups (A-Z)
+const PRESSURE_GROUPS: u8 = 26;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct BuehlmannModel {
     config: BuehlmannConfig,
     compartments: Vec<Compartment>,
     state: BuehlmannState,
     sim: bool,
+    // custom CNS coefficients table, default table if not set
+    cns_table: Option<Vec<CNSCoeffRow>>,
+    // ambient temperature samples (dive time, °C), not affecting the algorithm
+    temperature_trace: Vec<(Time, f64)>,
+}
+
+// fixed size tissue pressures and state snapshot for in-place simulations without model cloning
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TissueSnapshot {
+    compartments: [CompartmentPressures; MAX_COMPARTMENTS],
+    compartments_count: usize,
+    state: BuehlmannState,
 }
 
+// model state checkpoint for what-if planning
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    compartments: Vec<Compartment>,
+    state: BuehlmannState,
+}
+
+/// gradient factors line from GF low at GF low depth (first stop reference) to GF high at the surface
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GfLine {
+    pub gf_low_depth: Depth,
+    pub gf_low: GradientFactor,
+    pub gf_high: GradientFactor,
+}
+
+impl GfLine {
+    /// GF at depth on the line (GF low below GF low depth)
+    pub fn gf_at_depth(&self, depth: Depth) -> f64 {
+        if depth >= self.gf_low_depth {
+            return self.gf_low.into();
+        }
+        let gf_delta = (self.gf_high - self.gf_low) as f64;
+        self.gf_high as f64 - gf_delta * (depth.as_meters() / self.gf_low_depth.as_meters())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BuehlmannState {
     depth: Depth,
+    max_depth: Depth,
     time: Time,
+    // running integral of depth over time (meter-seconds) for average depth
+    depth_time: f64,
     gas: Gas,
     gf_low_depth: Option<Depth>,
     ox_tox: OxTox,
+    // ceiling reported with configured hysteresis
+    hysteretic_ceiling: Option<Depth>,
+    // adaptive ceiling simulated in place on record (adaptive ceiling type)
+    adaptive_ceiling: Option<Depth>,
 }
 
 impl Default for BuehlmannState {
     fn default() -> Self {
         Self {
             depth: Depth::zero(),
+            max_depth: Depth::zero(),
             time: Time::zero(),
+            depth_time: 0.,
             gas: Gas::air(),
             gf_low_depth: None,
             ox_tox: OxTox::default(),
+            hysteretic_ceiling: None,
+            adaptive_ceiling: None,
         }
     }
 }
 
+impl BuehlmannState {
+    // state equality with float values (depth, time, oxygen toxicity) compared within tolerance
+    fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        let within_tolerance = |a: f64, b: f64| (a - b).abs() <= tolerance;
+        let depths_within_tolerance = |a: Option<Depth>, b: Option<Depth>| match (a, b) {
+            (Some(a), Some(b)) => within_tolerance(a.as_meters(), b.as_meters()),
+            (None, None) => true,
+            _ => false,
+        };
+        within_tolerance(self.depth.as_meters(), other.depth.as_meters())
+            && within_tolerance(self.max_depth.as_meters(), other.max_depth.as_meters())
+            && within_tolerance(self.time.as_seconds(), other.time.as_seconds())
+            && self.gas == other.gas
+            && depths_within_tolerance(self.gf_low_depth, other.gf_low_depth)
+            && within_tolerance(self.ox_tox.cns(), other.ox_tox.cns())
+            && within_tolerance(self.ox_tox.otu(), other.ox_tox.otu())
+            && depths_within_tolerance(self.hysteretic_ceiling, other.hysteretic_ceiling)
+            && depths_within_tolerance(self.adaptive_ceiling, other.adaptive_ceiling)
+    }
+}
+
 impl DecoModel for BuehlmannModel {
     type ConfigType = BuehlmannConfig;
 
@@ -49,57 +145,61 @@ impl DecoModel for BuehlmannModel {
 
     /// initialize new Buehlmann (ZH-L16C) model with gradient factors
     fn new(config: BuehlmannConfig) -> Self {
-        // validate config
-        if let Err(e) = config.validate() {
-            panic!("Config error [{}]: {}", e.field, e.reason);
-        }
         // air as a default init gas
-        let initial_model_state = BuehlmannState::default();
-        let mut model = Self {
-            config,
-            compartments: vec![],
-            state: initial_model_state,
-            sim: false,
-        };
-        model.create_compartments(ZHL_16C_N2_16A_HE_VALUES, config);
-
-        model
+        Self::new_with_initial_gas(config, Gas::air())
     }
 
     /// record data: depth (meters), time (seconds), gas
+    /// (zero time record changes depth and gas only, tissues saturation unchanged but ceiling
+    /// and M-values recalculated for new depth)
     fn record(&mut self, depth: Depth, time: Time, gas: &Gas) {
         self.validate_depth(depth);
         self.state.depth = depth;
+        self.update_max_depth(depth);
         self.state.gas = *gas;
         self.state.time += time;
+        self.state.depth_time += depth.as_meters() * time.as_seconds();
         let record = RecordData { depth, time, gas };
         self.recalculate(record);
+        self.update_adaptive_ceiling();
+        self.update_hysteretic_ceiling();
     }
 
     /// model travel between depths in 1s intervals
+    /// (tissues and oxygen toxicity recalculated on each interval's depth, ox tox skipped on simulations
+    /// or if disabled for travel in config)
     // @todo: Schreiner equation instead of Haldane to avoid imprecise intervals
     fn record_travel(&mut self, target_depth: Depth, time: Time, gas: &Gas) {
         self.validate_depth(target_depth);
+        self.update_max_depth(target_depth);
         self.state.gas = *gas;
-        let mut current_depth = self.state.depth;
-        let distance = target_depth - current_depth;
-        let travel_time = time;
-        let dist_rate = distance.as_meters() / travel_time.as_seconds();
+        let start_depth = self.state.depth;
+        let distance = target_depth - start_depth;
+        let travel_time = time.as_seconds();
+        // linear travel contributes its mean depth over the whole travel time
+        self.state.depth_time += ((start_depth + target_depth).as_meters() / 2.) * travel_time;
         let mut i = 0;
-        while i < travel_time.as_seconds() as i32 {
+        while i < travel_time as i32 {
+            i += 1;
             self.state.time += Time::from_seconds(1.);
-            current_depth += Depth::from_meters(dist_rate);
+            // interpolated from start depth on each interval to avoid accumulating rounding errors
+            let current_depth = start_depth + distance * (i as f64 / travel_time);
             let record = RecordData {
                 depth: current_depth,
                 time: Time::from_seconds(1.),
                 gas,
             };
-            self.recalculate(record);
-            i += 1;
+            if self.config.oxtox_during_travel {
+                self.recalculate(record);
+            } else {
+                self.recalculate_compartments(&record);
+            }
         }
 
-        // align with target depth with lost precision @todo: round / bignumber?
+        // align with target depth on travel time not divisible into 1s intervals
         self.state.depth = target_depth;
+        self.update_adaptive_ceiling();
+        self.update_hysteretic_ceiling();
     }
 
     fn record_travel_with_rate(
@@ -115,77 +215,60 @@ impl DecoModel for BuehlmannModel {
     }
 
     fn ndl(&self) -> Time {
-        let mut ndl = Time::from_minutes(NDL_CUT_OFF_MINS.into());
-
         if self.in_deco() {
             return Time::zero();
         }
 
         // create a simulation model based on current model's state
         let mut sim_model = self.fork();
-
-        // iterate simulation model over 1min records until NDL cut-off or in deco
-        let interval = Time::from_minutes(1.);
-        for i in 0..NDL_CUT_OFF_MINS {
-            // @todo
-            sim_model.record(self.state.depth, interval, &self.state.gas);
-            if sim_model.in_deco() {
-                ndl = interval * i;
-                break;
+        let snapshot = sim_model.capture_tissues();
+
+        // bisect first full minute in deco within NDL cut-off (constant depth and gas,
+        // deco obligation only increasing with time), NDL as last minute without deco
+        let (mut no_deco_mins, mut in_deco_mins) = (0, NDL_CUT_OFF_MINS + 1);
+        while in_deco_mins - no_deco_mins > 1 {
+            let mid_mins = no_deco_mins + (in_deco_mins - no_deco_mins) / 2;
+            sim_model.restore_tissues(&snapshot);
+            sim_model.record(
+                self.state.depth,
+                Time::from_minutes(mid_mins.into()),
+                &self.state.gas,
+            );
+            match sim_model.in_deco() {
+                true => in_deco_mins = mid_mins,
+                false => no_deco_mins = mid_mins,
             }
         }
-        ndl
+        Time::from_minutes(no_deco_mins.into())
     }
 
     fn ceiling(&self) -> Depth {
-        let BuehlmannConfig {
-            deco_ascent_rate,
-            mut ceiling_type,
-            ..
-        } = self.config();
-        if self.sim {
-            ceiling_type = CeilingType::Actual;
-        }
-
-        let leading_comp: &Compartment = self.leading_comp();
-        let mut ceiling = match ceiling_type {
-            CeilingType::Actual => leading_comp.ceiling(),
-            CeilingType::Adaptive => {
-                let mut sim_model = self.fork();
-                let sim_gas = sim_model.dive_state().gas;
-                let mut calculated_ceiling = sim_model.ceiling();
-                loop {
-                    let sim_depth = sim_model.dive_state().depth;
-                    let sim_depth_cmp = sim_depth.partial_cmp(&Depth::zero());
-                    let sim_depth_at_surface = match sim_depth_cmp {
-                        Some(Ordering::Equal | Ordering::Less) => true,
-                        Some(Ordering::Greater) => false,
-                        None => panic!("Simulation depth incomparable to surface"),
-                    };
-                    if sim_depth_at_surface || sim_depth <= calculated_ceiling {
-                        break;
-                    }
-                    sim_model.record_travel_with_rate(
-                        calculated_ceiling,
-                        deco_ascent_rate,
-                        &sim_gas,
-                    );
-                    calculated_ceiling = sim_model.ceiling();
-                }
-                calculated_ceiling
+        // simulations (deco, NDL) always use current model ceiling
+        if let Some(hysteretic_ceiling) = self.state.hysteretic_ceiling {
+            if !self.is_sim() {
+                return hysteretic_ceiling;
             }
-        };
-
-        if self.config().round_ceiling() {
-            ceiling = Depth::from_meters(ceiling.as_meters().ceil());
         }
+        self.model_ceiling()
+    }
 
-        ceiling
+    fn gf_low_reference_depth(&self) -> Option<Depth> {
+        self.state.gf_low_depth
+    }
+
+    fn safe_to_surface(&self) -> bool {
+        let surface_pressure = self.config.surface_pressure;
+        self.compartments.iter().all(|comp| {
+            let (_, gf_high) = self.config.compartment_gf(comp.no);
+            let Supersaturation { gf_surf, .. } =
+                comp.supersaturation(surface_pressure, self.state.depth);
+            gf_surf <= gf_high.into()
+        })
     }
 
     fn deco(&self, gas_mixes: Vec<Gas>) -> Result<DecoRuntime, DecoCalculationError> {
-        let mut deco = Deco::default();
-        deco.calc(self.fork(), gas_mixes)
+        let mut sim_model = self.fork();
+        self.new_deco(&mut sim_model).calc(sim_model, gas_mixes)
     }
 
     fn config(&self) -> BuehlmannConfig {
@@ -195,6 +278,7 @@ impl DecoModel for BuehlmannModel {
     fn dive_state(&self) -> DiveState {
         let BuehlmannState {
             depth,
+            max_depth,
             time,
             gas,
             ox_tox,
@@ -202,6 +286,7 @@ impl DecoModel for BuehlmannModel {
         } = self.state;
         DiveState {
             depth,
+            max_depth,
             time,
             gas,
             ox_tox,
@@ -230,13 +315,45 @@ impl Sim for BuehlmannModel {
 }
 
 impl BuehlmannModel {
+    /// initialize new model with tissues saturated with given gas at surface
+    pub fn new_with_initial_gas(config: BuehlmannConfig, initial_gas: Gas) -> Self {
+        Self::init(config, initial_gas, &ZHL_16C_N2_16A_HE_VALUES)
+    }
+
+    /// initialize new model at given depth with tissues saturated with given gas at that depth
+    /// (full equilibrium, e.g. saturation diving or long exposure start)
+    pub fn at_equilibrium(config: BuehlmannConfig, depth: Depth, gas: &Gas) -> Self {
+        let mut model = Self::init(config, *gas, &ZHL_16C_N2_16A_HE_VALUES);
+        model.compartments = ZHL_16C_N2_16A_HE_VALUES
+            .iter()
+            .enumerate()
+            .map(|(i, comp_values)| {
+                Compartment::new_saturated(i as u8 + 1, *comp_values, config, *gas, depth)
+            })
+            .collect();
+        model.record(depth, Time::zero(), gas);
+
+        model
+    }
+
+    /// initialize new model with custom compartments set (eg. reduced or extended ZHL params)
+    pub fn new_with_compartments(config: BuehlmannConfig, zhl_values: &[ZHLParams]) -> Self {
+        Self::init(config, Gas::air(), zhl_values)
+    }
+
     /// set of current gradient factors (GF now, GF surface)
     pub fn supersaturation(&self) -> Supersaturation {
+        self.supersaturation_at_pressure(self.config.surface_pressure)
+    }
+
+    /// set of current gradient factors (GF now, GF surface) given arbitrary surface pressure,
+    /// e.g. after transition to altitude
+    pub fn supersaturation_at_pressure(&self, surface_pressure: MbarPressure) -> Supersaturation {
         let mut acc_gf_99 = 0.;
         let mut acc_gf_surf = 0.;
         for comp in self.compartments.iter() {
             let Supersaturation { gf_99, gf_surf } =
-                comp.supersaturation(self.config.surface_pressure, self.state.depth);
+                comp.supersaturation(surface_pressure, self.state.depth);
             if gf_99 > acc_gf_99 {
                 acc_gf_99 = gf_99;
             }
@@ -251,19 +368,784 @@ impl BuehlmannModel {
         }
     }
 
+    /// ppO2 of breathing gas at current depth
+    pub fn current_pp_o2(&self) -> Pressure {
+        self.state
+            .gas
+            .partial_pressures(self.state.depth, self.config.surface_pressure)
+            .o2
+    }
+
+    /// inspired gas partial pressures at current depth, considering configured water vapor pressure
+    pub fn current_inspired_partial_pressures(&self) -> PartialPressures {
+        self.state.gas.inspired_partial_pressures_with_water_vapor(
+            self.state.depth,
+            self.config.surface_pressure,
+            self.config.water_vapor_pressure,
+        )
+    }
+
+    /// ceiling after instantaneous switch to given gas at current depth (simulation ceiling),
+    /// M-values depend on depth and tissues loading only, so the switch itself doesn't change
+    /// the instantaneous ceiling - breathing gas affects subsequent loading
+    pub fn ceiling_on_gas(&self, gas: &Gas) -> Depth {
+        let mut sim_model = self.fork();
+        sim_model.record(self.state.depth, Time::zero(), gas);
+        sim_model.ceiling()
+    }
+
+    /// isobaric counterdiffusion risk on switch to given gas at current depth,
+    /// true if N2 (and H2) on-gassing exceeds He off-gassing in any of the fastest compartments
+    pub fn icd_risk_on_switch(&self, new_gas: &Gas) -> bool {
+        self.compartments.iter().take(ICD_COMPARTMENTS).any(|comp| {
+            comp.inert_pressure_rate(new_gas, self.state.depth, self.config.surface_pressure) > 0.
+        })
+    }
+
+    /// model with the higher tissue loading of each compartment of the two models (team planning),
+    /// current depth, time and gas of this model, models config and compartments set must match
+    pub fn max_loading_with(
+        &self,
+        other: &BuehlmannModel,
+    ) -> Result<BuehlmannModel, ConfigValidationErr> {
+        if self.config != other.config || self.compartments.len() != other.compartments.len() {
+            return Err(ConfigValidationErr::new(
+                "config",
+                MERGED_MODELS_CONFIG_ERR_MSG,
+            ));
+        }
+        let mut merged_model = self.clone();
+        for (comp, other_comp) in merged_model
+            .compartments
+            .iter_mut()
+            .zip(other.compartments.iter())
+        {
+            if other_comp.total_ip > comp.total_ip {
+                *comp = *other_comp;
+            }
+        }
+        // compartments keep their tolerable pressures, deeper GF low reference kept for further records
+        merged_model.state.gf_low_depth = match (self.state.gf_low_depth, other.state.gf_low_depth)
+        {
+            (Some(gf_low_depth), Some(other_gf_low_depth)) => {
+                Some(gf_low_depth.max(other_gf_low_depth))
+            }
+            (gf_low_depth, other_gf_low_depth) => gf_low_depth.or(other_gf_low_depth),
+        };
+        merged_model.state.hysteretic_ceiling = None;
+        merged_model.update_adaptive_ceiling();
+        merged_model.update_hysteretic_ceiling();
+
+        Ok(merged_model)
+    }
+
+    /// raw Buehlmann ceiling (GF 100) of leading compartment, unrounded, e.g. to show GF padding
+    /// over configured ceiling
+    pub fn raw_ceiling(&self) -> Depth {
+        self.compartments
+            .iter()
+            .map(|comp| comp.raw_ceiling())
+            .fold(Depth::zero(), Depth::max)
+    }
+
+    /// leading compartment's supersaturation gradient (bar) at current depth: total inert gas
+    /// pressure over ambient pressure (negative when on-gassing)
+    pub fn leading_gradient(&self) -> Pressure {
+        let p_amb = self
+            .state
+            .depth
+            .to_ambient_pressure(self.config.surface_pressure);
+        self.leading_comp().total_ip - p_amb
+    }
+
+    /// remaining NDL as a fraction (0-1) of NDL at current depth and gas with no accumulated
+    /// loading (surface-saturated tissues), e.g. for NDL ring gauge
+    pub fn ndl_fraction(&self) -> f64 {
+        let mut fresh_model = Self::init(self.config, Gas::air(), &self.zhl_params()).fork();
+        fresh_model.record(self.state.depth, Time::zero(), &self.state.gas);
+        let fresh_ndl = fresh_model.ndl();
+        if fresh_ndl <= Time::zero() {
+            return 0.;
+        }
+        (self.ndl() / fresh_ndl).as_seconds().clamp(0., 1.)
+    }
+
+    /// recreational pressure group (A-Z) of controlling compartment: residual N2 over surface
+    /// equilibrium on air as a fraction of surface N2 M-value headroom, split into 26 equal groups
+    /// (A: up to 1/26 incl. no residual N2, B: up to 2/26, .., Z: over 25/26 incl. M-value exceeded)
+    pub fn pressure_group(&self) -> char {
+        let p_surf = Depth::zero().to_ambient_pressure(self.config.surface_pressure);
+        let loading_fraction = self
+            .compartments
+            .iter()
+            .map(|comp| {
+                let (_, n2_a_coeff, n2_b_coeff, ..) = comp.params;
+                let surface_n2_ip = Compartment::new(comp.no, comp.params, self.config).n2_ip;
+                let surface_m_value = n2_a_coeff + p_surf / n2_b_coeff;
+                (comp.n2_ip - surface_n2_ip) / (surface_m_value - surface_n2_ip)
+            })
+            .fold(0., f64::max);
+        let group = ((loading_fraction * PRESSURE_GROUPS as f64).ceil() as u8)
+            .clamp(1, PRESSURE_GROUPS)
+            - 1;
+        (b'A' + group) as char
+    }
+
+    /// GF low depth (first stop depth before rounding) from current tissues state without deco
+    /// calculation, determined on a simulation model if not yet reached, zero if not in deco
+    pub fn gf_low_depth(&self) -> Depth {
+        match self.state.gf_low_depth {
+            Some(gf_low_depth) => gf_low_depth,
+            None => self.fork().deco_gf_low_depth().unwrap_or(Depth::zero()),
+        }
+    }
+
+    /// GF line for plotting, None if GF low depth not determined (not in deco)
+    pub fn gf_line(&self) -> Option<GfLine> {
+        let (gf_low, gf_high) = self.config.gf;
+        self.state.gf_low_depth.map(|gf_low_depth| GfLine {
+            gf_low_depth,
+            gf_low,
+            gf_high,
+        })
+    }
+
+    /// deco runtime with minimal TTS among gas switch strategies: each candidate deco gas used
+    /// or skipped, switching at gas MOD or waiting for the next stop (current gas always used,
+    /// ppO2 and END limits respected), default strategy preferred on equal TTS
+    pub fn optimize_deco(
+        &self,
+        candidate_gases: Vec<Gas>,
+    ) -> Result<DecoRuntime, DecoCalculationError> {
+        let current_gas = self.state.gas;
+        let deco_gases: Vec<Gas> = candidate_gases
+            .into_iter()
+            .filter(|gas| !Deco::gas_in_mixes(gas, &[current_gas]))
+            .collect();
+
+        let mut optimal_runtime: Option<DecoRuntime> = None;
+        let mut last_err = DecoCalculationError::EmptyGasList;
+        let default_switch_at_stops_only = self.config.switch_at_stops_only;
+        for switch_at_stops_only in [default_switch_at_stops_only, !default_switch_at_stops_only] {
+            let mut sim_model = self.fork();
+            sim_model.config.switch_at_stops_only = switch_at_stops_only;
+            // gas subsets as bit masks, all deco gases first
+            for gases_mask in (0..(1_u32 << deco_gases.len())).rev() {
+                let mut gas_mixes = vec![current_gas];
+                gas_mixes.extend(
+                    deco_gases
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| gases_mask & (1 << i) != 0)
+                        .map(|(_, gas)| *gas),
+                );
+                let deco_runtime = match sim_model.deco(gas_mixes) {
+                    Ok(deco_runtime) => deco_runtime,
+                    Err(e) => {
+                        last_err = e;
+                        continue;
+                    }
+                };
+                let is_optimal = match &optimal_runtime {
+                    Some(optimal_runtime) => deco_runtime.tts < optimal_runtime.tts,
+                    None => true,
+                };
+                if is_optimal {
+                    optimal_runtime = Some(deco_runtime);
+                }
+            }
+        }
+
+        optimal_runtime.ok_or(last_err)
+    }
+
+    /// why each gas mix would or would not be switched to at current depth in deco calculation
+    /// (e.g. over MOD, over END), for multi-gas deco plan diagnostics
+    pub fn explain_gas_selection(&self, gas_mixes: Vec<Gas>) -> Vec<GasSelectionReason> {
+        Deco::explain_gas_selection(self, gas_mixes)
+    }
+
+    /// deco stages calculated lazily (e.g. only next stops needed), same stages as deco runtime
+    pub fn deco_iter(
+        &self,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<DecoIter<BuehlmannModel>, DecoCalculationError> {
+        let mut sim_model = self.fork();
+        self.new_deco(&mut sim_model)
+            .stages_iter(sim_model, gas_mixes)
+    }
+
+    /// time-weighted average depth of all recorded segments (travel segments contribute
+    /// their mean depth), zero if no time recorded
+    pub fn average_depth(&self) -> Depth {
+        let total_time = self.state.time.as_seconds();
+        if total_time <= 0. {
+            return Depth::zero();
+        }
+        Depth::from_meters(self.state.depth_time / total_time)
+    }
+
+    /// oxygen clock: time at current depth and gas until CNS reaches 100%
+    /// (zero if already reached, capped at cut-off if CNS not accruing)
+    pub fn cns_time_remaining(&self) -> Time {
+        let cut_off = Time::from_minutes(CNS_TIME_REMAINING_CUT_OFF_MINS);
+        let cns = self.state.ox_tox.cns();
+        if cns >= 100. {
+            return Time::zero();
+        }
+        // CNS accrual linear at constant ppO2, rate from a single step on ox tox state copy
+        let step = Time::from_minutes(1.);
+        let mut ox_tox = self.state.ox_tox;
+        ox_tox.recalculate_with_cns_table(
+            &RecordData {
+                depth: self.state.depth,
+                time: step,
+                gas: &self.state.gas,
+            },
+            self.config.surface_pressure,
+            self.config.cns_limit_over_max_pp_o2,
+            self.cns_table(),
+        );
+        let cns_per_step = ox_tox.cns() - cns;
+        if cns_per_step <= 0. {
+            return cut_off;
+        }
+        let remaining = Time::from_minutes(((100. - cns) / cns_per_step) * step.as_minutes());
+        match remaining < cut_off {
+            true => remaining,
+            false => cut_off,
+        }
+    }
+
+    /// reset dive clock for next dive in series: time and depth (incl. max and average depth) zeroed,
+    /// tissue loading and oxygen toxicity preserved
+    pub fn reset_clock(&mut self) {
+        self.state.time = Time::zero();
+        self.state.depth = Depth::zero();
+        self.state.max_depth = Depth::zero();
+        self.state.depth_time = 0.;
+    }
+
+    /// model equality with compartments pressures and dive state compared within tolerance
+    /// (e.g. same profile recorded with different step granularity), config compared exactly
+    pub fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        self.config == other.config
+            && self.compartments.len() == other.compartments.len()
+            && self
+                .compartments
+                .iter()
+                .zip(other.compartments.iter())
+                .all(|(comp, other_comp)| comp.approx_eq(other_comp, tolerance))
+            && self.state.approx_eq(&other.state, tolerance)
+    }
+
+    /// ZHL params of model compartments in use, fastest compartment first
+    pub fn zhl_params(&self) -> Vec<ZHLParams> {
+        self.compartments.iter().map(|comp| comp.params).collect()
+    }
+
     pub fn tissues(&self) -> Vec<Compartment> {
         self.compartments.clone()
     }
 
+    /// N2, He and H2 inert pressures per compartment (e.g. He washout during trimix deco)
+    pub fn inert_loading(&self) -> Vec<InertLoading> {
+        self.compartments
+            .iter()
+            .map(|comp| {
+                let he_n2_ratio = match (comp.n2_ip > 0., comp.he_ip > 0.) {
+                    (true, _) => comp.he_ip / comp.n2_ip,
+                    (false, true) => f64::INFINITY,
+                    (false, false) => 0.,
+                };
+                InertLoading {
+                    compartment_no: comp.no,
+                    n2_ip: comp.n2_ip,
+                    he_ip: comp.he_ip,
+                    h2_ip: comp.h2_ip,
+                    he_n2_ratio,
+                }
+            })
+            .collect()
+    }
+
+    /// fallible record, returns error on invalid depth instead of panicking
+    pub fn try_record(&mut self, depth: Depth, time: Time, gas: &Gas) -> Result<(), RecordError> {
+        self.check_depth(depth)?;
+        self.record(depth, time, gas);
+        Ok(())
+    }
+
+    /// record with ambient water temperature (°C) stored in temperature trace at dive time after
+    /// the record, temperature not (yet) affecting the algorithm
+    pub fn record_with_env(&mut self, depth: Depth, time: Time, gas: &Gas, temp_c: f64) {
+        self.record(depth, time, gas);
+        self.temperature_trace.push((self.state.time, temp_c));
+    }
+
+    /// recorded ambient temperature samples (dive time, °C)
+    pub fn temperature_trace(&self) -> &[(Time, f64)] {
+        &self.temperature_trace
+    }
+
+    /// record profile segments (e.g. imported dive log), each as linear travel from previous depth,
+    /// all segments validated before recording
+    pub fn record_profile(&mut self, segments: &[ProfileSegment]) -> Result<(), RecordError> {
+        for segment in segments {
+            self.check_depth(segment.depth)?;
+        }
+        for segment in segments {
+            self.record_travel(segment.depth, segment.time, &segment.gas);
+        }
+        Ok(())
+    }
+
+    /// time until reported ceiling (configured ceiling type and hysteresis) moves to next shallower
+    /// deco stop holding current depth and gas, None if no ceiling or no stop boundary crossed
+    /// within NDL cut-off (e.g. ceiling not getting shallower at depth)
+    pub fn time_to_next_ceiling_change(&self) -> Option<Time> {
+        // non-simulation copy, ceiling reported as by ceiling()
+        let mut model = Self {
+            sim: false,
+            ..self.clone()
+        };
+        let ceiling = model.ceiling();
+        if ceiling <= Depth::zero() {
+            return None;
+        }
+        let stop_window = Depth::from_meters(DEFAULT_CEILING_WINDOW);
+        let next_stop = Deco::stop_depth(ceiling) - stop_window;
+
+        let interval = Time::from_seconds(1.);
+        let mut time = Time::zero();
+        let cut_off = Time::from_minutes(NDL_CUT_OFF_MINS.into());
+        while time < cut_off {
+            model.record(self.state.depth, interval, &self.state.gas);
+            time += interval;
+            if Deco::stop_depth(model.ceiling()).approx_le(next_stop) {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// surface interval (breathing air) until all tissues return to initial surface saturation
+    pub fn desaturation_time(&self) -> Time {
+        let surface_saturation: Vec<Pressure> = self
+            .compartments
+            .iter()
+            .map(|comp| Compartment::new(comp.no, comp.params, self.config).total_ip)
+            .collect();
+        self.surface_interval_until(|sim_model| {
+            sim_model
+                .compartments
+                .iter()
+                .zip(surface_saturation.iter())
+                .all(|(comp, saturation)| {
+                    (comp.total_ip - saturation).abs() <= DESATURATION_EPSILON
+                })
+        })
+    }
+
+    /// surface interval (breathing air) until all tissues tolerate aircraft cabin pressure (~0.75 bar)
+    /// within compartment GF high, capped at 72h
+    pub fn no_fly_time(&self) -> Time {
+        self.surface_interval_until(|sim_model| {
+            sim_model.compartments.iter().all(|comp| {
+                let (_, gf_high) = sim_model.config.compartment_gf(comp.no);
+                let Supersaturation { gf_surf, .. } =
+                    comp.supersaturation(CABIN_PRESSURE, sim_model.state.depth);
+                gf_surf <= gf_high as f64
+            })
+        })
+    }
+
+    /// surfacing GF after replaying ascent segments on a simulation model,
+    /// ascent to surface with deco ascent rate added if last segment ends below surface
+    pub fn simulate_surface_gf(&self, segments: &[ProfileSegment]) -> f64 {
+        let mut sim_model = self.fork();
+        for segment in segments {
+            sim_model.record_travel(segment.depth, segment.time, &segment.gas);
+        }
+        let DiveState { depth, gas, .. } = sim_model.dive_state();
+        if depth > Depth::zero() {
+            sim_model.record_travel_with_rate(Depth::zero(), self.config.deco_ascent_rate, &gas);
+        }
+        let Supersaturation { gf_surf, .. } = sim_model.supersaturation();
+        gf_surf
+    }
+
+    /// supersaturation after descent to target depth with given rate followed by time at depth,
+    /// simulated on a fork leaving model state untouched
+    pub fn preview_descent(
+        &self,
+        target_depth: Depth,
+        rate: AscentRatePerMinute,
+        time_at_depth: Time,
+        gas: &Gas,
+    ) -> Supersaturation {
+        let mut sim_model = self.fork();
+        sim_model.record_square(target_depth, rate, time_at_depth, gas);
+        sim_model.supersaturation()
+    }
+
+    /// instantaneous switch to gas at current depth, gas has to be within ppO2 limit (MOD) and not
+    /// hypoxic (ppO2 at least min breathable ppO2). Deco ppO2 limit for deco gases (more oxygen-rich
+    /// than current gas), bottom ppO2 limit for other gases (e.g. bottom gas after travel gas)
+    pub fn switch_gas(&mut self, gas: &Gas) -> Result<(), RecordError> {
+        let (o2, ..) = gas.fractions();
+        let (current_o2, ..) = self.state.gas.fractions();
+        let pp_o2_limit = match o2 > current_o2 {
+            true => self.config.deco_pp_o2_limit,
+            false => self.config.bottom_pp_o2_limit,
+        };
+        let pp_o2 = gas
+            .partial_pressures(self.state.depth, self.config.surface_pressure)
+            .o2;
+        if self.state.depth > gas.max_operating_depth(pp_o2_limit) {
+            return Err(RecordError::PpO2LimitExceeded(pp_o2));
+        }
+        if pp_o2 < MIN_BREATHABLE_PP_O2 {
+            return Err(RecordError::PpO2BelowMin(pp_o2));
+        }
+        self.record(self.state.depth, Time::zero(), gas);
+        Ok(())
+    }
+
+    /// GF high (GF low unchanged) for which surfacing GF after following deco runtime is closest to target
+    pub fn solve_gf_high_for_surface(
+        &self,
+        target_gf_surf: f64,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<GradientFactor, DecoCalculationError> {
+        let (gf_low, _) = self.config.gf;
+        let mut low = gf_low;
+        let mut high = 100;
+        // binary search, surfacing GF increasing with GF high
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.surface_gf_after_deco(mid, gas_mixes.clone())? < target_gf_surf {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        // closest of boundary candidates
+        let mut gf_high = low;
+        if low > gf_low {
+            let below_diff =
+                (self.surface_gf_after_deco(low - 1, gas_mixes.clone())? - target_gf_surf).abs();
+            let diff = (self.surface_gf_after_deco(low, gas_mixes)? - target_gf_surf).abs();
+            if below_diff < diff {
+                gf_high = low - 1;
+            }
+        }
+
+        Ok(gf_high)
+    }
+
+    /// record depth at absolute dive runtime timestamp, time delta calculated from current model time
+    pub fn record_at(
+        &mut self,
+        timestamp: Time,
+        depth: Depth,
+        gas: &Gas,
+    ) -> Result<(), RecordError> {
+        if timestamp < self.state.time {
+            return Err(RecordError::TimestampInPast(timestamp));
+        }
+        self.try_record(depth, timestamp - self.state.time, gas)
+    }
+
+    /// model state checkpoint (tissues, depth, time, gas, oxygen toxicity)
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            compartments: self.compartments.clone(),
+            state: self.state,
+        }
+    }
+
+    /// restore model to checkpoint state as a non-simulation model
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        let Checkpoint {
+            compartments,
+            state,
+        } = checkpoint;
+        self.compartments = compartments;
+        self.state = state;
+        self.sim = false;
+        self.update_adaptive_ceiling();
+    }
+
+    /// record travel to new depth on current gas and recalculate deco runtime
+    pub fn ascend_step(
+        &mut self,
+        new_depth: Depth,
+        rate: AscentRatePerMinute,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<DecoRuntime, DecoCalculationError> {
+        let current_gas = self.state.gas;
+        self.record_travel_with_rate(new_depth, rate, &current_gas);
+        self.deco(gas_mixes)
+    }
+
+    /// first deco stop depth, None if no deco obligation
+    pub fn first_stop_depth(
+        &self,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<Option<Depth>, DecoCalculationError> {
+        let DecoRuntime { deco_stages, .. } = self.deco(gas_mixes)?;
+        let first_stop = deco_stages
+            .into_iter()
+            .find(|stage| stage.stage_type == DecoStageType::DecoStop)
+            .map(|stop| stop.start_depth);
+
+        Ok(first_stop)
+    }
+
+    /// ascent duration from current depth to first deco stop (incl. gas switches on the way),
+    /// None if no deco stops
+    pub fn time_to_first_stop(
+        &self,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<Option<Time>, DecoCalculationError> {
+        let DecoRuntime { deco_stages, .. } = self.deco(gas_mixes)?;
+        let first_stop_index = deco_stages
+            .iter()
+            .position(|stage| stage.stage_type == DecoStageType::DecoStop);
+        let time_to_first_stop = first_stop_index.map(|i| {
+            deco_stages[..i]
+                .iter()
+                .fold(Time::zero(), |acc, stage| acc + stage.duration)
+        });
+
+        Ok(time_to_first_stop)
+    }
+
+    /// deco profile (time, depth, gas, ceiling) sampled every interval of dive time along deco runtime,
+    /// including current and final surface state
+    pub fn deco_profile_samples(
+        &self,
+        gas_mixes: Vec<Gas>,
+        interval: Time,
+    ) -> Result<Vec<DecoSample>, DecoCalculationError> {
+        self.sample_deco(gas_mixes, interval, |model| model.deco_sample())
+    }
+
+    /// leading compartment number (controlling ceiling) sampled every interval of dive time along deco runtime
+    pub fn controlling_compartment_trace(
+        &self,
+        gas_mixes: Vec<Gas>,
+        interval: Time,
+    ) -> Result<Vec<(Time, u8)>, DecoCalculationError> {
+        self.sample_deco(gas_mixes, interval, |model| {
+            (model.state.time, model.leading_comp().no)
+        })
+    }
+
+    /// minimum gas (rock bottom) in liters needed to surface from current state
+    /// given stress SAC rate (liters / min at surface pressure)
+    pub fn min_gas(
+        &self,
+        stress_sac: f64,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<f64, DecoCalculationError> {
+        let DecoRuntime { deco_stages, .. } = self.deco(gas_mixes)?;
+        let min_gas = deco_stages.iter().fold(0., |acc, stage| {
+            let mean_depth = (stage.start_depth + stage.end_depth) / 2.;
+            let mean_amb_pressure = mean_depth.to_ambient_pressure(self.config.surface_pressure);
+            acc + (stress_sac * stage.duration.as_minutes() * mean_amb_pressure)
+        });
+
+        Ok(min_gas)
+    }
+
+    /// CNS coefficients table used for CNS calculation
+    pub fn cns_table(&self) -> &[CNSCoeffRow] {
+        self.cns_table.as_deref().unwrap_or(&CNS_COEFFICIENTS)
+    }
+
+    /// override default (NOAA based) CNS coefficients table for subsequent records,
+    /// ppO2 ranges have to be ascending, contiguous and non-overlapping
+    pub fn set_cns_table(
+        &mut self,
+        cns_table: Vec<CNSCoeffRow>,
+    ) -> Result<(), ConfigValidationErr> {
+        if !is_valid_cns_table(&cns_table) {
+            return Err(ConfigValidationErr::new("cns_table", CNS_TABLE_ERR_MSG));
+        }
+        self.cns_table = Some(cns_table);
+        Ok(())
+    }
+
     pub fn update_config(
         &mut self,
         new_config: BuehlmannConfig,
     ) -> Result<(), ConfigValidationErr> {
         new_config.validate()?;
+        // GF low depth calculated with previous gradient factors
+        if new_config.gf != self.config.gf
+            || new_config.per_compartment_gf != self.config.per_compartment_gf
+            || new_config.gf_low_reference != self.config.gf_low_reference
+        {
+            self.state.gf_low_depth = None;
+        }
+        // ceiling reported with previous config
+        self.state.hysteretic_ceiling = None;
         self.config = new_config;
+        for compartment in self.compartments.iter_mut() {
+            compartment.update_model_config(new_config);
+        }
+        self.update_adaptive_ceiling();
         Ok(())
     }
 
+    fn capture_tissues(&self) -> TissueSnapshot {
+        let compartments_count = self.compartments.len();
+        let mut compartments = [CompartmentPressures::default(); MAX_COMPARTMENTS];
+        for (pressures, comp) in compartments.iter_mut().zip(self.compartments.iter()) {
+            *pressures = comp.pressures();
+        }
+        TissueSnapshot {
+            compartments,
+            compartments_count,
+            state: self.state,
+        }
+    }
+
+    fn restore_tissues(&mut self, snapshot: &TissueSnapshot) {
+        for (comp, pressures) in self
+            .compartments
+            .iter_mut()
+            .zip(snapshot.compartments[..snapshot.compartments_count].iter())
+        {
+            comp.restore_pressures(pressures);
+        }
+        self.state = snapshot.state;
+    }
+
+    // ceiling given current tissues saturation, without hysteresis
+    fn model_ceiling(&self) -> Depth {
+        let ceiling = match self.effective_ceiling_type() {
+            CeilingType::Actual | CeilingType::CachedAdaptive => self.leading_comp().ceiling(),
+            // simulated on a copy only if not calculated in place on record
+            CeilingType::Adaptive => match self.state.adaptive_ceiling {
+                Some(adaptive_ceiling) => adaptive_ceiling,
+                None => self.fork().adaptive_ceiling(),
+            },
+            CeilingType::GfSurface => self.gf_surface_ceiling(),
+        };
+
+        self.rounded_ceiling(ceiling)
+    }
+
+    // adaptive ceiling simulated in place for reported ceiling, cleared for other ceiling types
+    fn update_adaptive_ceiling(&mut self) {
+        self.state.adaptive_ceiling = match self.effective_ceiling_type() {
+            CeilingType::Adaptive => Some(self.adaptive_ceiling()),
+            _ => None,
+        };
+    }
+
+    // ceiling increases immediately, decreases only when cleared by more than hysteresis margin
+    // (trailing model ceiling by margin), cleared ceiling reported without delay
+    fn update_hysteretic_ceiling(&mut self) {
+        let hysteresis = self.config.ceiling_hysteresis;
+        if self.is_sim() || hysteresis <= Depth::zero() {
+            return;
+        }
+        let model_ceiling = self.model_ceiling();
+        let hysteretic_ceiling = match self.state.hysteretic_ceiling {
+            Some(reported_ceiling) if model_ceiling > Depth::zero() => reported_ceiling
+                .min(model_ceiling + hysteresis)
+                .max(model_ceiling),
+            _ => model_ceiling,
+        };
+        self.state.hysteretic_ceiling = Some(hysteretic_ceiling);
+    }
+
+    // deco calculation for configured ceiling type, simulation model used for in-place adaptive ceiling
+    fn new_deco(&self, sim_model: &mut Self) -> Deco {
+        match self.effective_ceiling_type() {
+            // adaptive ceiling calculated once, reused for first stop determination
+            CeilingType::CachedAdaptive => {
+                let adaptive_ceiling = sim_model.adaptive_ceiling();
+                Deco::default().with_first_stop_ceiling(self.rounded_ceiling(adaptive_ceiling))
+            }
+            _ => Deco::default(),
+        }
+    }
+
+    // ceiling type with adaptive ceiling disabled for simulations
+    fn effective_ceiling_type(&self) -> CeilingType {
+        match self.sim {
+            true => CeilingType::Actual,
+            false => self.config.ceiling_type,
+        }
+    }
+
+    fn rounded_ceiling(&self, ceiling: Depth) -> Depth {
+        match self.config.round_ceiling() {
+            true => ceiling.ceil_m(),
+            false => ceiling,
+        }
+    }
+
+    // model ceiling (without hysteresis) calculated without cloning the model, state restored after
+    // adaptive simulation
+    fn ceiling_in_place(&mut self) -> Depth {
+        match self.effective_ceiling_type() {
+            CeilingType::Actual | CeilingType::CachedAdaptive | CeilingType::GfSurface => {
+                self.model_ceiling()
+            }
+            CeilingType::Adaptive => {
+                let ceiling = self.adaptive_ceiling();
+                self.rounded_ceiling(ceiling)
+            }
+        }
+    }
+
+    // deepest compartment ceiling at its GF high, within surfacing GF limit when cleared
+    fn gf_surface_ceiling(&self) -> Depth {
+        self.compartments
+            .iter()
+            .map(|comp| {
+                let (_, gf_high) = self.config.compartment_gf(comp.no);
+                comp.ceiling_at_gf(gf_high)
+            })
+            .fold(Depth::zero(), Depth::max)
+    }
+
+    // ceiling considering off-gassing during ascent, simulated in-place
+    fn adaptive_ceiling(&mut self) -> Depth {
+        let snapshot = self.capture_tissues();
+        let is_sim = self.sim;
+        self.sim = true;
+
+        let deco_ascent_rate = self.config.deco_ascent_rate;
+        let sim_gas = self.state.gas;
+        let mut calculated_ceiling = self.ceiling();
+        loop {
+            let sim_depth = self.state.depth;
+            let sim_depth_cmp = sim_depth.partial_cmp(&Depth::zero());
+            let sim_depth_at_surface = match sim_depth_cmp {
+                Some(Ordering::Equal | Ordering::Less) => true,
+                Some(Ordering::Greater) => false,
+                None => panic!("Simulation depth incomparable to surface"),
+            };
+            if sim_depth_at_surface || sim_depth.approx_le(calculated_ceiling) {
+                break;
+            }
+            self.record_travel_with_rate(calculated_ceiling, deco_ascent_rate, &sim_gas);
+            calculated_ceiling = self.ceiling();
+        }
+
+        self.restore_tissues(&snapshot);
+        self.sim = is_sim;
+
+        calculated_ceiling
+    }
+
     fn leading_comp(&self) -> &Compartment {
         let mut leading_comp: &Compartment = &self.compartments[0];
         for compartment in &self.compartments[1..] {
@@ -289,10 +1171,46 @@ impl BuehlmannModel {
         &mut comps[leading_comp_index]
     }
 
-    fn create_compartments(&mut self, zhl_values: [ZHLParams; 16], config: BuehlmannConfig) {
+    fn init(config: BuehlmannConfig, initial_gas: Gas, zhl_values: &[ZHLParams]) -> Self {
+        // validate config
+        if let Err(e) = config.validate() {
+            panic!("Config error [{}]: {}", e.field, e.reason);
+        }
+        if zhl_values.is_empty() || zhl_values.len() > MAX_COMPARTMENTS {
+            panic!(
+                "Invalid compartments count [{}], expected 1-{}",
+                zhl_values.len(),
+                MAX_COMPARTMENTS
+            );
+        }
+        let initial_model_state = BuehlmannState {
+            gas: initial_gas,
+            ..BuehlmannState::default()
+        };
+        let mut model = Self {
+            config,
+            compartments: vec![],
+            state: initial_model_state,
+            sim: false,
+            cns_table: None,
+            temperature_trace: vec![],
+        };
+        model.create_compartments(zhl_values, config, initial_gas);
+        model.update_adaptive_ceiling();
+
+        model
+    }
+
+    fn create_compartments(
+        &mut self,
+        zhl_values: &[ZHLParams],
+        config: BuehlmannConfig,
+        initial_gas: Gas,
+    ) {
         let mut compartments: Vec<Compartment> = vec![];
-        for (i, comp_values) in zhl_values.into_iter().enumerate() {
-            let compartment = Compartment::new(i as u8 + 1, comp_values, config);
+        for (i, comp_values) in zhl_values.iter().enumerate() {
+            let compartment =
+                Compartment::new_with_initial_gas(i as u8 + 1, *comp_values, config, initial_gas);
             compartments.push(compartment);
         }
         self.compartments = compartments;
@@ -306,12 +1224,28 @@ impl BuehlmannModel {
     }
 
     fn recalculate_compartments(&mut self, record: &RecordData) {
-        let (gf_low, gf_high) = self.config.gf;
+        let config = self.config;
         for compartment in self.compartments.iter_mut() {
-            compartment.recalculate(record, gf_high, self.config.surface_pressure);
+            let (_, gf_high) = config.compartment_gf(compartment.no);
+            compartment.recalculate(record, gf_high, config.surface_pressure);
+        }
+
+        // compartment-specific GF slopes, leading compartment depends on its GF so all recalculated
+        let per_compartment_gf = config
+            .per_compartment_gf
+            .filter(|per_compartment_gf| per_compartment_gf.iter().any(|gf| *gf != config.gf));
+        if let Some(per_compartment_gf) = per_compartment_gf {
+            if per_compartment_gf
+                .iter()
+                .any(|(gf_low, gf_high)| gf_low != gf_high)
+            {
+                self.recalculate_all_tissues_with_compartment_gf(record);
+            }
+            return;
         }
 
         // recalc
+        let (gf_low, gf_high) = config.gf;
         if gf_high != gf_low {
             let max_gf = self.max_gf(self.config.gf, record.depth);
 
@@ -335,6 +1269,32 @@ impl BuehlmannModel {
         }
     }
 
+    fn recalculate_all_tissues_with_compartment_gf(&mut self, record: &RecordData) {
+        let gf_low_depth = self.deco_gf_low_depth();
+        let max_gfs: Vec<GradientFactor> = self
+            .compartments
+            .iter()
+            .map(|comp| {
+                let compartment_gf = self.config.compartment_gf(comp.no);
+                match gf_low_depth {
+                    Some(gf_low_depth) => {
+                        self.sloped_max_gf(compartment_gf, gf_low_depth, record.depth)
+                    }
+                    None => compartment_gf.1,
+                }
+            })
+            .collect();
+        let recalc_record = RecordData {
+            depth: record.depth,
+            time: Time::zero(),
+            gas: record.gas,
+        };
+        let surface_pressure = self.config.surface_pressure;
+        for (compartment, max_gf) in self.compartments.iter_mut().zip(max_gfs) {
+            compartment.recalculate(&recalc_record, max_gf, surface_pressure);
+        }
+    }
+
     fn recalculate_leading_compartment_with_gf(
         &mut self,
         record: &RecordData,
@@ -353,42 +1313,91 @@ impl BuehlmannModel {
     }
 
     fn recalculate_ox_tox(&mut self, record: &RecordData) {
-        self.state
-            .ox_tox
-            .recalculate(record, self.config().surface_pressure);
+        let cns_table = self.cns_table.as_deref().unwrap_or(&CNS_COEFFICIENTS);
+        self.state.ox_tox.recalculate_with_cns_table(
+            record,
+            self.config.surface_pressure,
+            self.config.cns_limit_over_max_pp_o2,
+            cns_table,
+        );
     }
 
     fn max_gf(&mut self, gf: GradientFactors, depth: Depth) -> GradientFactor {
         let (gf_low, gf_high) = gf;
-        let in_deco = self.ceiling() > Depth::zero();
-        if !in_deco {
+        // single GF, no slope to determine GF low depth for
+        if gf_low == gf_high {
             return gf_high;
         }
+        match self.deco_gf_low_depth() {
+            Some(gf_low_depth) => self.sloped_max_gf(gf, gf_low_depth, depth),
+            None => gf_high,
+        }
+    }
 
-        let gf_low_depth = match self.state.gf_low_depth {
-            Some(gf_low_depth) => gf_low_depth,
-            None => {
-                // find GF low depth
-                let mut sim_model = self.fork();
-                let sim_gas = sim_model.state.gas;
-                let mut target_depth = sim_model.state.depth;
-                while target_depth > Depth::zero() {
-                    let mut sim_record_depth = target_depth - Depth::from_meters(1.);
-                    if sim_record_depth < Depth::zero() {
-                        sim_record_depth = Depth::zero();
-                    }
-                    sim_model.record(sim_record_depth, Time::zero(), &sim_gas);
-                    let Supersaturation { gf_99, .. } = sim_model.supersaturation();
-                    if gf_99 >= gf_low.into() {
-                        break;
-                    }
-                    target_depth = sim_record_depth;
-                }
-                self.state.gf_low_depth = Some(target_depth);
-                target_depth
+    // GF low depth (determined once in deco), None if not in deco
+    fn deco_gf_low_depth(&mut self) -> Option<Depth> {
+        let in_deco = self.ceiling_in_place() > Depth::zero();
+        if !in_deco {
+            return None;
+        }
+        if let Some(gf_low_depth) = self.state.gf_low_depth {
+            return Some(gf_low_depth);
+        }
+
+        // find GF low depth using in-place zero-time records, restored afterwards
+        let snapshot = self.capture_tissues();
+        let config = self.config;
+        let sim_gas = self.state.gas;
+        let mut target_depth = self.state.depth;
+        while target_depth > Depth::zero() {
+            let mut sim_record_depth = target_depth - Depth::from_meters(1.);
+            if sim_record_depth < Depth::zero() {
+                sim_record_depth = Depth::zero();
+            }
+            let sim_record = RecordData {
+                depth: sim_record_depth,
+                time: Time::zero(),
+                gas: &sim_gas,
+            };
+            self.state.depth = sim_record_depth;
+            for compartment in self.compartments.iter_mut() {
+                let (_, gf_high) = config.compartment_gf(compartment.no);
+                compartment.recalculate(&sim_record, gf_high, config.surface_pressure);
+            }
+            if self.gf_low_reached() {
+                break;
             }
+            target_depth = sim_record_depth;
+        }
+        self.restore_tissues(&snapshot);
+        let gf_low_depth = match config.gf_low_reference {
+            GfLowReference::MaxSupersaturation => target_depth,
+            GfLowReference::FirstStop => target_depth
+                .ceil_to(Depth::from_meters(DEFAULT_CEILING_WINDOW))
+                .min(self.state.depth),
         };
+        self.state.gf_low_depth = Some(gf_low_depth);
+        Some(gf_low_depth)
+    }
 
+    // any compartment's GF99 at its GF low
+    fn gf_low_reached(&self) -> bool {
+        self.compartments.iter().any(|comp| {
+            let (gf_low, _) = self.config.compartment_gf(comp.no);
+            let Supersaturation { gf_99, .. } =
+                comp.supersaturation(self.config.surface_pressure, self.state.depth);
+            gf_99 >= gf_low.into()
+        })
+    }
+
+    // max gf at depth given GF low depth, GF low below it and sloped towards GF high at surface
+    fn sloped_max_gf(
+        &self,
+        gf: GradientFactors,
+        gf_low_depth: Depth,
+        depth: Depth,
+    ) -> GradientFactor {
+        let (gf_low, _) = gf;
         if depth > gf_low_depth {
             return gf_low;
         }
@@ -403,16 +1412,128 @@ impl BuehlmannModel {
         depth: Depth,
     ) -> GradientFactor {
         let (gf_low, gf_high) = gf;
+        if gf_low_depth <= Depth::zero() {
+            return gf_high;
+        }
         let slope_point: f64 = gf_high as f64
             - (((gf_high - gf_low) as f64) / gf_low_depth.as_meters()) * depth.as_meters();
 
-        slope_point as u8
+        // float jitter around GF low depth kept within slope bounds
+        slope_point.clamp(gf_low as f64, gf_high as f64) as u8
+    }
+
+    // replay deco runtime on simulation model, sampling model every interval of dive time
+    fn sample_deco<T>(
+        &self,
+        gas_mixes: Vec<Gas>,
+        interval: Time,
+        sample: impl Fn(&Self) -> T,
+    ) -> Result<Vec<T>, DecoCalculationError> {
+        if interval <= Time::zero() {
+            return Err(DecoCalculationError::InvalidSamplingInterval { interval });
+        }
+        let DecoRuntime { deco_stages, .. } = self.deco(gas_mixes)?;
+        let mut sim_model = self.fork();
+        let mut samples = vec![sample(&sim_model)];
+        let mut time_since_sample = Time::zero();
+        for stage in deco_stages.iter() {
+            // zero-time stages (gas switch)
+            if stage.duration <= Time::zero() {
+                sim_model.record(stage.end_depth, Time::zero(), &stage.gas);
+                continue;
+            }
+            let mut stage_elapsed = Time::zero();
+            while stage_elapsed < stage.duration {
+                let mut step = interval - time_since_sample;
+                if (stage_elapsed + step) > stage.duration {
+                    step = stage.duration - stage_elapsed;
+                }
+                stage_elapsed += step;
+                time_since_sample += step;
+                let travelled_fraction = (stage_elapsed / stage.duration).as_seconds();
+                let step_depth = stage.start_depth
+                    + ((stage.end_depth - stage.start_depth) * travelled_fraction);
+                sim_model.record_travel(step_depth, step, &stage.gas);
+                if time_since_sample >= interval {
+                    samples.push(sample(&sim_model));
+                    time_since_sample = Time::zero();
+                }
+            }
+        }
+        if time_since_sample > Time::zero() {
+            samples.push(sample(&sim_model));
+        }
+
+        Ok(samples)
+    }
+
+    // surfacing GF after following deco runtime calculated with given GF high
+    fn surface_gf_after_deco(
+        &self,
+        gf_high: GradientFactor,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<f64, DecoCalculationError> {
+        let (gf_low, _) = self.config.gf;
+        let mut trial_model = self.fork();
+        trial_model.config.gf = (gf_low, gf_high);
+        for compartment in trial_model.compartments.iter_mut() {
+            compartment.update_model_config(trial_model.config);
+        }
+        let DecoRuntime { deco_stages, .. } = trial_model.deco(gas_mixes)?;
+        let segments: Vec<ProfileSegment> = deco_stages
+            .iter()
+            .map(|stage| ProfileSegment {
+                depth: stage.end_depth,
+                time: stage.duration,
+                gas: stage.gas,
+            })
+            .collect();
+
+        Ok(trial_model.simulate_surface_gf(&segments))
+    }
+
+    // surface interval on simulation model until predicate satisfied (or cut-off reached)
+    fn surface_interval_until(&self, predicate: impl Fn(&Self) -> bool) -> Time {
+        let mut sim_model = self.fork();
+        let interval = Time::from_minutes(1.);
+        let cut_off = Time::from_minutes(SURFACE_INTERVAL_CUT_OFF_MINS);
+        let mut time = Time::zero();
+        while time < cut_off && !predicate(&sim_model) {
+            sim_model.surface_interval(interval, &Gas::air());
+            time += interval;
+        }
+        time
+    }
+
+    fn deco_sample(&self) -> DecoSample {
+        DecoSample {
+            time: self.state.time,
+            depth: self.state.depth,
+            gas: self.state.gas,
+            ceiling: self.ceiling(),
+        }
+    }
+
+    fn update_max_depth(&mut self, depth: Depth) {
+        if depth > self.state.max_depth {
+            self.state.max_depth = depth;
+        }
     }
 
     fn validate_depth(&self, depth: Depth) {
+        if let Err(e) = self.check_depth(depth) {
+            panic!("{e}");
+        }
+    }
+
+    fn check_depth(&self, depth: Depth) -> Result<(), RecordError> {
         if depth < Depth::zero() {
-            panic!("Invalid depth [{}]", depth);
+            return Err(RecordError::InvalidDepth(depth));
+        }
+        if depth > self.config.max_depth {
+            return Err(RecordError::DepthExceedsModelLimit(depth));
         }
+        Ok(())
     }
 }
 
@@ -478,6 +1599,18 @@ mod tests {
         assert_eq!(model.max_gf(gf, Depth::from_meters(14.)), 40);
     }
 
+    #[test]
+    fn test_single_gf_skips_gf_low_depth() {
+        let gf = (85, 85);
+        let mut model = BuehlmannModel::new(BuehlmannConfig::new().with_single_gf(gf.1));
+        let air = Gas::air();
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        model.record_travel_with_rate(Depth::from_meters(9.), 9., &air);
+        assert!(model.ceiling() > Depth::zero());
+        assert_eq!(model.max_gf(gf, Depth::from_meters(9.)), 85);
+        assert_eq!(model.state.gf_low_depth, None);
+    }
+
     #[test]
     fn test_gf_slope_point() {
         let gf = (30, 85);
@@ -509,6 +1642,108 @@ mod tests {
         assert_eq!(initial_gfs, surface_interval_gfs);
     }
 
+    #[test]
+    fn test_initial_gas_seeding() {
+        let air_model =
+            BuehlmannModel::new_with_initial_gas(BuehlmannConfig::default(), Gas::air());
+        assert_eq!(
+            air_model.compartments,
+            BuehlmannModel::default().compartments
+        );
+
+        let heliox = Gas::new(0.21, 0.79);
+        let heliox_model = BuehlmannModel::new_with_initial_gas(BuehlmannConfig::default(), heliox);
+        assert_eq!(heliox_model.state.gas, heliox);
+        let expected_he_ip = heliox
+            .inspired_partial_pressures(Depth::zero(), heliox_model.config.surface_pressure)
+            .he;
+        for comp in heliox_model.compartments.iter() {
+            assert_eq!(comp.he_ip, expected_he_ip);
+            assert_eq!(comp.n2_ip, 0.);
+        }
+
+        // same total inert pressure, different M-values for He-loaded tissues
+        for (air_comp, heliox_comp) in air_model
+            .compartments
+            .iter()
+            .zip(heliox_model.compartments.iter())
+        {
+            let surface_pressure = air_model.config.surface_pressure;
+            let air_supersaturation = air_comp.supersaturation(surface_pressure, Depth::zero());
+            let heliox_supersaturation =
+                heliox_comp.supersaturation(surface_pressure, Depth::zero());
+            assert_ne!(air_supersaturation, heliox_supersaturation);
+            assert!(heliox_supersaturation.gf_surf < 0.);
+        }
+        assert_eq!(heliox_model.supersaturation().gf_surf, 0.);
+    }
+
+    #[test]
+    fn test_tissues_capture_restore() {
+        let mut model = BuehlmannModel::new(BuehlmannConfig::new().with_gradient_factors(30, 70));
+        let air = Gas::air();
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+        let initial_ceiling = model.ceiling();
+        let initial_compartments = model.compartments.clone();
+
+        let snapshot = model.capture_tissues();
+        model.record(Depth::from_meters(40.), Time::from_minutes(10.), &air);
+        assert_ne!(model.ceiling(), initial_ceiling);
+
+        model.restore_tissues(&snapshot);
+        assert_eq!(model.ceiling(), initial_ceiling);
+        assert_eq!(model.compartments, initial_compartments);
+        assert_eq!(model.state, snapshot.state);
+    }
+
+    #[test]
+    fn test_in_place_adaptive_ceiling() {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_ceiling_type(CeilingType::Adaptive);
+        let mut model = BuehlmannModel::new(config);
+        let air = Gas::air();
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+        // clone-based reference
+        let mut sim_model = model.fork();
+        let mut clone_based_ceiling = sim_model.ceiling();
+        while sim_model.state.depth > Depth::zero() && sim_model.state.depth > clone_based_ceiling {
+            sim_model.record_travel_with_rate(clone_based_ceiling, 10., &air);
+            clone_based_ceiling = sim_model.ceiling();
+        }
+
+        let initial_model = model.clone();
+        let in_place_ceiling = model.ceiling_in_place();
+        assert_eq!(in_place_ceiling, clone_based_ceiling);
+        assert_eq!(model.ceiling(), clone_based_ceiling);
+        assert_eq!(model.compartments, initial_model.compartments);
+        assert_eq!(model.state, initial_model.state);
+        assert!(!model.is_sim());
+    }
+
+    #[test]
+    fn test_adaptive_ceiling_calculated_on_record() {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_ceiling_type(CeilingType::Adaptive);
+        let mut model = BuehlmannModel::new(config);
+        let air = Gas::air();
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+        // reported ceiling reused from record, equal to clone-based simulation
+        let clone_based_ceiling = model.fork().adaptive_ceiling();
+        assert_eq!(model.state.adaptive_ceiling, Some(clone_based_ceiling));
+        assert_eq!(model.ceiling(), clone_based_ceiling);
+
+        model
+            .update_config(config.with_ceiling_type(CeilingType::Actual))
+            .unwrap();
+        assert_eq!(model.state.adaptive_ceiling, None);
+        model.update_config(config).unwrap();
+        assert_eq!(model.state.adaptive_ceiling, Some(clone_based_ceiling));
+    }
+
     #[test]
     fn test_updating_config() {
         let mut model = BuehlmannModel::default();
@@ -536,6 +1771,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_updating_gf_resets_gf_low_depth() {
+        let air = Gas::air();
+        let depth = Depth::from_meters(40.);
+        let time = Time::from_minutes(30.);
+        let new_gf = (50, 85);
+
+        let mut model = BuehlmannModel::new(BuehlmannConfig::new().with_gradient_factors(30, 70));
+        model.record(depth, time, &air);
+        let initial_gf_low_depth = model.state.gf_low_depth;
+        assert!(initial_gf_low_depth.is_some());
+
+        // unchanged GF keeps GF low depth
+        model
+            .update_config(model.config().with_round_ceiling(true))
+            .unwrap();
+        assert_eq!(model.state.gf_low_depth, initial_gf_low_depth);
+
+        model
+            .update_config(model.config().with_gradient_factors(new_gf.0, new_gf.1))
+            .unwrap();
+        assert_eq!(model.state.gf_low_depth, None);
+
+        let mut fresh_model = BuehlmannModel::new(
+            BuehlmannConfig::new()
+                .with_gradient_factors(new_gf.0, new_gf.1)
+                .with_round_ceiling(true),
+        );
+        fresh_model.record(depth, time, &air);
+
+        let test_depth = Depth::from_meters(12.);
+        assert_eq!(
+            model.max_gf(new_gf, test_depth),
+            fresh_model.max_gf(new_gf, test_depth)
+        );
+        assert_eq!(model.state.gf_low_depth, fresh_model.state.gf_low_depth);
+        assert_ne!(model.state.gf_low_depth, initial_gf_low_depth);
+    }
+
     #[test]
     fn test_ndl_0_if_in_deco() {
         let mut model = BuehlmannModel::new(
@@ -549,4 +1823,22 @@ mod tests {
         let ndl = model.ndl();
         assert_eq!(ndl, Time::zero());
     }
+
+    #[test]
+    fn test_gf_slope_point_clamped() {
+        let model = BuehlmannModel::new(BuehlmannConfig::default());
+        let gf = (30, 70);
+        let gf_low_depth = Depth::from_meters(21.);
+        for depth in [
+            Depth::from_meters(21. + 1e-9),
+            Depth::from_meters(21.5),
+            Depth::from_meters(-1e-9),
+        ] {
+            let slope_point = model.gf_slope_point(gf, gf_low_depth, depth);
+            assert!((30..=70).contains(&slope_point), "GF [{}]", slope_point);
+        }
+        assert_eq!(model.gf_slope_point(gf, gf_low_depth, Depth::zero()), 70);
+        assert_eq!(model.gf_slope_point(gf, gf_low_depth, gf_low_depth), 30);
+        assert_eq!(model.gf_slope_point(gf, Depth::zero(), Depth::zero()), 70);
+    }
 }
@@ -1,4 +1,4 @@
-use super::zhl_values::{ZHLParam, ZHLParams};
+use super::zhl_values::{zhl_h2_params, ZHLParam, ZHLParams};
 use crate::{
     common::{
         Depth, GradientFactor, InertGas, MbarPressure, PartialPressures, Pressure, RecordData,
@@ -6,7 +6,11 @@ use crate::{
     BuehlmannConfig, Gas, Time,
 };
 
+// alveolar CO2 pressure (40 mm Hg)
+const ALVEOLAR_CO2_PRESSURE: Pressure = 0.0534;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Compartment {
     // tissue number
     pub no: u8,
@@ -16,7 +20,9 @@ pub struct Compartment {
     pub he_ip: Pressure,
     // nitrogen saturation pressure
     pub n2_ip: Pressure,
-    // total inert gas pressure (He + N2)
+    // hydrogen saturation pressure
+    pub h2_ip: Pressure,
+    // total inert gas pressure (He + N2 + H2)
     pub total_ip: Pressure,
     // M-value (original)
     pub m_value_raw: Pressure,
@@ -24,6 +30,8 @@ pub struct Compartment {
     pub m_value_calc: Pressure,
     // compartment'a Buehlmann params (N2 half time, n2 'a' coefficient, n2 'b' coefficient, He half time, ..)
     pub params: ZHLParams,
+    // gradient factor applied in last tolerable pressure calculation
+    max_gf: GradientFactor,
     // Buehlmann model config (gradient factors, surface pressure)
     model_config: BuehlmannConfig,
 }
@@ -34,74 +42,231 @@ pub struct Supersaturation {
     pub gf_surf: f64,
 }
 
+// compartment inert gas pressures and values derived from them (without params and config),
+// lightweight copy for in-place simulations
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct CompartmentPressures {
+    min_tolerable_amb_pressure: Pressure,
+    he_ip: Pressure,
+    n2_ip: Pressure,
+    h2_ip: Pressure,
+    total_ip: Pressure,
+    m_value_raw: Pressure,
+    m_value_calc: Pressure,
+    max_gf: GradientFactor,
+}
+
+/// compartment inert gases loading breakdown
+#[derive(Debug, PartialEq, Clone)]
+pub struct InertLoading {
+    pub compartment_no: u8,
+    pub n2_ip: Pressure,
+    pub he_ip: Pressure,
+    pub h2_ip: Pressure,
+    // He to N2 inert pressure ratio (infinite if He without N2, 0 if neither)
+    pub he_n2_ratio: f64,
+}
+
 impl Compartment {
     pub fn new(no: u8, params: ZHLParams, model_config: BuehlmannConfig) -> Self {
-        let init_gas = Gas::air();
-        let init_gas_compound_pressures =
-            init_gas.inspired_partial_pressures(Depth::zero(), model_config.surface_pressure);
+        Self::new_with_initial_gas(no, params, model_config, Gas::air())
+    }
+
+    // new compartment saturated with given gas at surface pressure
+    pub fn new_with_initial_gas(
+        no: u8,
+        params: ZHLParams,
+        model_config: BuehlmannConfig,
+        init_gas: Gas,
+    ) -> Self {
+        Self::new_saturated(no, params, model_config, init_gas, Depth::zero())
+    }
+
+    // new compartment saturated (at equilibrium) with given gas at given depth
+    pub fn new_saturated(
+        no: u8,
+        params: ZHLParams,
+        model_config: BuehlmannConfig,
+        init_gas: Gas,
+        depth: Depth,
+    ) -> Self {
+        let init_gas_compound_pressures = init_gas.inspired_partial_pressures_with_water_vapor(
+            depth,
+            model_config.surface_pressure,
+            Self::alveolar_pressure_deduction(&model_config),
+        );
         let n2_ip = init_gas_compound_pressures.n2;
         let he_ip = init_gas_compound_pressures.he;
+        let h2_ip = init_gas_compound_pressures.h2;
 
         let mut compartment = Self {
             no,
             params,
             n2_ip,
             he_ip,
-            total_ip: he_ip + n2_ip,
+            h2_ip,
+            total_ip: he_ip + n2_ip + h2_ip,
             m_value_raw: 0.,  // initial, recalculated later
             m_value_calc: 0., // initial, recalculated later
             min_tolerable_amb_pressure: 0.,
+            max_gf: model_config.compartment_gf(no).1,
             model_config,
         };
 
         // calculate initial minimal tolerable ambient pressure
-        let (_, gf_high) = model_config.gf;
-        compartment.m_value_raw =
-            compartment.m_value(Depth::zero(), model_config.surface_pressure, 100);
+        let (_, gf_high) = model_config.compartment_gf(no);
+        compartment.m_value_raw = compartment.m_value(depth, model_config.surface_pressure, 100);
         compartment.m_value_calc = compartment.m_value_raw;
         compartment.min_tolerable_amb_pressure = compartment.min_tolerable_amb_pressure(gf_high);
 
         compartment
     }
 
+    // inert gases and tolerable pressures equality within tolerance
+    pub(crate) fn approx_eq(&self, other: &Self, tolerance: Pressure) -> bool {
+        let within_tolerance = |a: Pressure, b: Pressure| (a - b).abs() <= tolerance;
+        self.no == other.no
+            && within_tolerance(self.he_ip, other.he_ip)
+            && within_tolerance(self.n2_ip, other.n2_ip)
+            && within_tolerance(self.h2_ip, other.h2_ip)
+            && within_tolerance(self.total_ip, other.total_ip)
+            && within_tolerance(self.m_value_calc, other.m_value_calc)
+            && within_tolerance(
+                self.min_tolerable_amb_pressure,
+                other.min_tolerable_amb_pressure,
+            )
+    }
+
     // recalculate tissue inert gasses saturation and tolerable pressure
+    // (saturation unchanged on zero time records, only depth dependent M-values recalculated)
     pub fn recalculate(
         &mut self,
         record: &RecordData,
         max_gf: GradientFactor,
         surface_pressure: MbarPressure,
     ) {
-        let (he_inert_pressure, n2_inert_pressure) =
-            self.compartment_inert_pressure(record, surface_pressure);
-
-        self.he_ip = he_inert_pressure;
-        self.n2_ip = n2_inert_pressure;
-        self.total_ip = he_inert_pressure + n2_inert_pressure;
+        if record.time > Time::zero() {
+            let (he_inert_pressure, n2_inert_pressure, h2_inert_pressure) =
+                self.compartment_inert_pressure(record, surface_pressure);
+
+            self.he_ip = he_inert_pressure;
+            self.n2_ip = n2_inert_pressure;
+            self.h2_ip = h2_inert_pressure;
+            self.total_ip = he_inert_pressure + n2_inert_pressure + h2_inert_pressure;
+        }
 
         // @todo m_value tuple
         self.m_value_raw = self.m_value(record.depth, surface_pressure, 100);
         self.m_value_calc = self.m_value(record.depth, surface_pressure, max_gf);
 
         self.min_tolerable_amb_pressure = self.min_tolerable_amb_pressure(max_gf);
+        self.max_gf = max_gf;
+    }
+
+    // N2 and He half-times (minutes)
+    pub fn half_times(&self) -> (f64, f64) {
+        let (n2_half_time, _, _, he_half_time, _, _) = self.params;
+        (n2_half_time, he_half_time)
+    }
+
+    // instantaneous total inert gas pressure change rate (bar/min) when breathing gas at depth
+    pub fn inert_pressure_rate(
+        &self,
+        gas: &Gas,
+        depth: Depth,
+        surface_pressure: MbarPressure,
+    ) -> Pressure {
+        let PartialPressures {
+            n2: n2_pp,
+            he: he_pp,
+            h2: h2_pp,
+            ..
+        } = gas.inspired_partial_pressures_with_water_vapor(
+            depth,
+            surface_pressure,
+            Self::alveolar_pressure_deduction(&self.model_config),
+        );
+        let (n2_half_time, he_half_time) = self.half_times();
+        let (h2_half_time, ..) = zhl_h2_params(self.params);
+
+        // d/dt of Haldane equation at t=0: (Pi - Po) * ln2 / half-time
+        ((n2_pp - self.n2_ip) / n2_half_time
+            + (he_pp - self.he_ip) / he_half_time
+            + (h2_pp - self.h2_ip) / h2_half_time)
+            * std::f64::consts::LN_2
+    }
+
+    // replace model config (eg. on model config update)
+    pub(crate) fn pressures(&self) -> CompartmentPressures {
+        CompartmentPressures {
+            min_tolerable_amb_pressure: self.min_tolerable_amb_pressure,
+            he_ip: self.he_ip,
+            n2_ip: self.n2_ip,
+            h2_ip: self.h2_ip,
+            total_ip: self.total_ip,
+            m_value_raw: self.m_value_raw,
+            m_value_calc: self.m_value_calc,
+            max_gf: self.max_gf,
+        }
+    }
+
+    pub(crate) fn restore_pressures(&mut self, pressures: &CompartmentPressures) {
+        self.min_tolerable_amb_pressure = pressures.min_tolerable_amb_pressure;
+        self.he_ip = pressures.he_ip;
+        self.n2_ip = pressures.n2_ip;
+        self.h2_ip = pressures.h2_ip;
+        self.total_ip = pressures.total_ip;
+        self.m_value_raw = pressures.m_value_raw;
+        self.m_value_calc = pressures.m_value_calc;
+        self.max_gf = pressures.max_gf;
+    }
+
+    pub(crate) fn update_model_config(&mut self, model_config: BuehlmannConfig) {
+        self.model_config = model_config;
+    }
+
+    // weighted and GF adjusted M-value line coefficients (a, b)
+    pub fn m_value_coefficients(&self) -> (Pressure, Pressure) {
+        let weighted_zhl_params = self.weighted_zhl_params(self.he_ip, self.n2_ip, self.h2_ip);
+        let (_, a_coeff_adjusted, b_coeff_adjusted) =
+            self.max_gf_adjusted_zhl_params(weighted_zhl_params, self.max_gf);
+
+        (a_coeff_adjusted, b_coeff_adjusted)
+    }
+
+    // M-value at given depth using current weighted and GF adjusted coefficients
+    pub fn m_value_at(&self, depth: Depth, surface_pressure: MbarPressure) -> Pressure {
+        self.m_value(depth, surface_pressure, self.max_gf)
     }
 
     // tissue ceiling as depth
     pub fn ceiling(&self) -> Depth {
-        let mut ceil = (self.min_tolerable_amb_pressure
-            - (self.model_config.surface_pressure as f64 / 1000.))
-            * 10.;
+        let ceil = Depth::from_ambient_pressure(
+            self.min_tolerable_amb_pressure,
+            self.model_config.surface_pressure,
+        );
         // cap ceiling at 0 if min tolerable leading compartment pressure depth equivalent negative
-        if ceil < 0. {
-            ceil = 0.;
-        }
+        ceil.max(Depth::zero())
+    }
 
-        Depth::from_meters(ceil)
+    // ceiling at GF 100 (raw Buehlmann M-value), regardless of gradient factors
+    pub fn raw_ceiling(&self) -> Depth {
+        self.ceiling_at_gf(100)
+    }
+
+    // ceiling at given GF applied at every depth (no GF slope)
+    pub fn ceiling_at_gf(&self, gf: GradientFactor) -> Depth {
+        let ceil = Depth::from_ambient_pressure(
+            self.min_tolerable_amb_pressure(gf),
+            self.model_config.surface_pressure,
+        );
+        ceil.max(Depth::zero())
     }
 
     // tissue supersaturation (gf99, surface gf)
     pub fn supersaturation(&self, surface_pressure: MbarPressure, depth: Depth) -> Supersaturation {
-        let p_surf = (surface_pressure as f64) / 1000.;
-        let p_amb = p_surf + (depth.as_meters() / 10.);
+        let p_surf = Depth::zero().to_ambient_pressure(surface_pressure);
+        let p_amb = depth.to_ambient_pressure(surface_pressure);
         let m_value = self.m_value_raw;
         let m_value_surf = self.m_value(Depth::zero(), surface_pressure, 100);
         let gf_99 = ((self.total_ip - p_amb) / (m_value - p_amb)) * 100.;
@@ -116,11 +281,10 @@ impl Compartment {
         surface_pressure: MbarPressure,
         max_gf: GradientFactor,
     ) -> Pressure {
-        let weighted_zhl_params = self.weighted_zhl_params(self.he_ip, self.n2_ip);
+        let weighted_zhl_params = self.weighted_zhl_params(self.he_ip, self.n2_ip, self.h2_ip);
         let (_, a_coeff_adjusted, b_coeff_adjusted) =
             self.max_gf_adjusted_zhl_params(weighted_zhl_params, max_gf);
-        let p_surf = (surface_pressure as f64) / 1000.;
-        let p_amb = p_surf + (depth.as_meters() / 10.);
+        let p_amb = depth.to_ambient_pressure(surface_pressure);
 
         a_coeff_adjusted + (p_amb / b_coeff_adjusted)
     }
@@ -130,21 +294,28 @@ impl Compartment {
         &self,
         record: &RecordData,
         surface_pressure: MbarPressure,
-    ) -> (Pressure, Pressure) {
-        // (he, n2)
+    ) -> (Pressure, Pressure, Pressure) {
+        // (he, n2, h2)
         let RecordData { depth, time, gas } = record;
         let PartialPressures {
             n2: n2_pp,
             he: he_pp,
+            h2: h2_pp,
             ..
-        } = gas.inspired_partial_pressures(*depth, surface_pressure);
+        } = gas.inspired_partial_pressures_with_water_vapor(
+            *depth,
+            surface_pressure,
+            Self::alveolar_pressure_deduction(&self.model_config),
+        );
 
         // partial pressure of inert gases in inspired gas (adjusted alveoli water vapor pressure)
         let he_inspired_pp = he_pp;
         let n2_inspired = n2_pp;
+        let h2_inspired = h2_pp;
 
         // tissue saturation pressure change for inert gasses
         let (n2_half_time, _, _, he_half_time, ..) = self.params;
+        let (h2_half_time, ..) = zhl_h2_params(self.params);
         let he_p_comp_delta = self.compartment_pressure_delta_haldane(
             InertGas::Helium,
             he_inspired_pp,
@@ -158,11 +329,32 @@ impl Compartment {
             n2_half_time,
         );
 
+        let h2_p_comp_delta = self.compartment_pressure_delta_haldane(
+            InertGas::Hydrogen,
+            h2_inspired,
+            *time,
+            h2_half_time,
+        );
+
         // inert gasses pressures after applying delta P
         let he_final = self.he_ip + he_p_comp_delta;
         let n2_final = self.n2_ip + n2_p_comp_delta;
+        let h2_final = self.h2_ip + h2_p_comp_delta;
+
+        (he_final, n2_final, h2_final)
+    }
 
-        (he_final, n2_final)
+    // ambient pressure deduction for alveolar inert gasses: water vapor and optional RQ-adjusted CO2 (Schreiner)
+    fn alveolar_pressure_deduction(model_config: &BuehlmannConfig) -> Pressure {
+        let BuehlmannConfig {
+            water_vapor_pressure,
+            respiratory_quotient,
+            ..
+        } = model_config;
+        match respiratory_quotient {
+            Some(rq) => water_vapor_pressure - (((1. - rq) / rq) * ALVEOLAR_CO2_PRESSURE),
+            None => *water_vapor_pressure,
+        }
     }
 
     // compartment pressure change for inert gas (Haldane equation)
@@ -176,6 +368,7 @@ impl Compartment {
         let inert_gas_load = match inert_gas {
             InertGas::Helium => self.he_ip,
             InertGas::Nitrogen => self.n2_ip,
+            InertGas::Hydrogen => self.h2_ip,
         };
 
         // (Pi - Po)(1 - e^(-0.693t/half-time))
@@ -184,33 +377,46 @@ impl Compartment {
 
     // tissue tolerable ambient pressure using GF slope, weighted Buehlmann ZHL params based on tissue inert gasses saturation proportions
     fn min_tolerable_amb_pressure(&self, max_gf: GradientFactor) -> Pressure {
-        let weighted_zhl_params = self.weighted_zhl_params(self.he_ip, self.n2_ip);
+        let weighted_zhl_params = self.weighted_zhl_params(self.he_ip, self.n2_ip, self.h2_ip);
         let (_, a_coefficient_adjusted, b_coefficient_adjusted) =
             self.max_gf_adjusted_zhl_params(weighted_zhl_params, max_gf);
 
         (self.total_ip - a_coefficient_adjusted) * b_coefficient_adjusted
     }
 
-    // weighted ZHL params (half time, a coefficient, b coefficient) based on N2 and He params and inert gasses proportions in tissue
+    // weighted ZHL params (half time, a coefficient, b coefficient) based on N2, He and H2 params and inert gasses proportions in tissue
     fn weighted_zhl_params(
         &self,
         he_pp: Pressure,
         n2_pp: Pressure,
+        h2_pp: Pressure,
     ) -> (ZHLParam, ZHLParam, ZHLParam) {
         fn weighted_param(
-            he_param: ZHLParam,
-            he_pp: Pressure,
-            n2_param: ZHLParam,
-            n2_pp: Pressure,
+            (he_param, he_pp): (ZHLParam, Pressure),
+            (n2_param, n2_pp): (ZHLParam, Pressure),
+            (h2_param, h2_pp): (ZHLParam, Pressure),
         ) -> ZHLParam {
-            ((he_param * he_pp) + (n2_param * n2_pp)) / (he_pp + n2_pp)
+            ((he_param * he_pp) + (n2_param * n2_pp) + (h2_param * h2_pp)) / (he_pp + n2_pp + h2_pp)
         }
         let (n2_half_time, n2_a_coeff, n2_b_coeff, he_half_time, he_a_coeff, he_b_coeff) =
             self.params;
+        let (h2_half_time, h2_a_coeff, h2_b_coeff) = zhl_h2_params(self.params);
         (
-            weighted_param(he_half_time, he_pp, n2_half_time, n2_pp),
-            weighted_param(he_a_coeff, he_pp, n2_a_coeff, n2_pp),
-            weighted_param(he_b_coeff, he_pp, n2_b_coeff, n2_pp),
+            weighted_param(
+                (he_half_time, he_pp),
+                (n2_half_time, n2_pp),
+                (h2_half_time, h2_pp),
+            ),
+            weighted_param(
+                (he_a_coeff, he_pp),
+                (n2_a_coeff, n2_pp),
+                (h2_a_coeff, h2_pp),
+            ),
+            weighted_param(
+                (he_b_coeff, he_pp),
+                (n2_b_coeff, n2_pp),
+                (h2_b_coeff, h2_pp),
+            ),
         )
     }
 
@@ -255,16 +461,24 @@ mod tests {
                 min_tolerable_amb_pressure: -0.257127315,
                 he_ip: 0.0,
                 n2_ip: 0.750737,
+                h2_ip: 0.0,
                 total_ip: 0.750737,
                 m_value_raw: 3.265840594059406,
                 m_value_calc: 3.265840594059406,
                 params: (4.0, 1.2599, 0.505, 1.51, 1.7424, 0.4245),
+                max_gf: 100,
                 // mocked config and state
                 model_config: BuehlmannConfig::default(),
             }
         );
     }
 
+    #[test]
+    fn test_half_times() {
+        assert_eq!(comp_1().half_times(), (4., 1.51));
+        assert_eq!(comp_5().half_times(), (27., 10.21));
+    }
+
     #[test]
     fn test_m_value_raw() {
         let mut comp_1 = comp_1();
@@ -310,10 +524,48 @@ mod tests {
         assert_eq!(comp.total_ip, 1.2850179204911072);
     }
 
+    #[test]
+    fn test_m_value_coefficients() {
+        let mut comp = comp_5();
+        let air = Gas::air();
+        let record = RecordData {
+            depth: Depth::from_meters(30.),
+            time: Time::from_minutes(10.),
+            gas: &air,
+        };
+        comp.recalculate(&record, 100, 1013);
+        let (a_coeff, b_coeff) = comp.m_value_coefficients();
+        assert_eq!((a_coeff, b_coeff), (0.6200, 0.8126));
+
+        comp.recalculate(&record, 70, 1013);
+        let (a_coeff_gf, b_coeff_gf) = comp.m_value_coefficients();
+        assert!(a_coeff_gf < a_coeff);
+        assert!(b_coeff_gf > b_coeff);
+        assert_eq!(comp.m_value_at(record.depth, 1013), comp.m_value_calc);
+    }
+
+    #[test]
+    fn test_m_value_at_surface() {
+        let mut comp = comp_5();
+        let air = Gas::air();
+        let record = RecordData {
+            depth: Depth::from_meters(30.),
+            time: Time::from_minutes(10.),
+            gas: &air,
+        };
+        comp.recalculate(&record, 100, 1013);
+        let surface_m_value = comp.m_value_at(Depth::zero(), 1013);
+        assert_eq!(surface_m_value, comp.m_value(Depth::zero(), 1013, 100));
+
+        let Supersaturation { gf_surf, .. } = comp.supersaturation(1013, record.depth);
+        let expected_gf_surf = ((comp.total_ip - 1.013) / (surface_m_value - 1.013)) * 100.;
+        assert_eq!(gf_surf, expected_gf_surf);
+    }
+
     #[test]
     fn test_weighted_params_trimix() {
         let comp = comp_1();
-        let weighted_params = comp.weighted_zhl_params(0.5, 1. - (0.18 + 0.5));
+        let weighted_params = comp.weighted_zhl_params(0.5, 1. - (0.18 + 0.5), 0.);
         assert_eq!(
             weighted_params,
             (2.481707317073171, 1.5541073170731705, 0.4559146341463414)
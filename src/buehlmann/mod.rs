@@ -4,5 +4,6 @@ mod compartment;
 mod zhl_values;
 
 pub use buehlmann_config::BuehlmannConfig;
-pub use buehlmann_model::BuehlmannModel;
-pub use compartment::{Compartment, Supersaturation};
+pub use buehlmann_model::{BuehlmannModel, Checkpoint, GfLine};
+pub use compartment::{Compartment, InertLoading, Supersaturation};
+pub use zhl_values::{ZHLParam, ZHLParams, ZHL_16C_N2_16A_HE_VALUES};
@@ -1,6 +1,21 @@
 pub type ZHLParam = f64;
 // N2 half-time, N2 a coefficient, N2 b coefficient, He half-time, He a coefficient, H2 b coefficient
 pub type ZHLParams = (ZHLParam, ZHLParam, ZHLParam, ZHLParam, ZHLParam, ZHLParam);
+// H2 half-time, H2 a coefficient, H2 b coefficient
+pub type ZHLH2Params = (ZHLParam, ZHLParam, ZHLParam);
+
+// N2 to H2 half-time ratio by Graham's law (sqrt of molar masses ratio, 28 / 2)
+const N2_TO_H2_HALF_TIME_RATIO: f64 = 3.7417;
+
+// experimental H2 params: half-times scaled from N2 by diffusivity, coefficients assumed as He
+pub fn zhl_h2_params(params: ZHLParams) -> ZHLH2Params {
+    let (n2_half_time, _, _, _, he_a_coeff, he_b_coeff) = params;
+    (
+        n2_half_time / N2_TO_H2_HALF_TIME_RATIO,
+        he_a_coeff,
+        he_b_coeff,
+    )
+}
 
 pub const ZHL_16C_N2_16A_HE_VALUES: [ZHLParams; 16] = [
     (4., 1.2599, 0.5050, 1.51, 01.7424, 0.4245),
@@ -0,0 +1,50 @@
+use super::{AscentRatePerMinute, Depth};
+
+/// ascent / descent rate
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AscentRate {
+    m_per_min: AscentRatePerMinute,
+}
+
+impl From<AscentRatePerMinute> for AscentRate {
+    fn from(m_per_min: AscentRatePerMinute) -> Self {
+        Self::from_m_per_min(m_per_min)
+    }
+}
+
+impl AscentRate {
+    pub fn from_m_per_min(val: AscentRatePerMinute) -> Self {
+        Self { m_per_min: val }
+    }
+    pub fn from_ft_per_min(val: f64) -> Self {
+        Self {
+            m_per_min: Depth::from_feet(val).as_meters(),
+        }
+    }
+    pub fn as_m_per_min(&self) -> AscentRatePerMinute {
+        self.m_per_min
+    }
+    pub fn as_ft_per_min(&self) -> f64 {
+        Depth::from_meters(self.m_per_min).as_feet()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_ft_per_min() {
+        let rate = AscentRate::from_ft_per_min(30.);
+        assert!((rate.as_m_per_min() - 9.144).abs() < 1e-9);
+        assert!((rate.as_ft_per_min() - 30.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_from_m_per_min() {
+        let rate: AscentRate = 9.0.into();
+        assert_eq!(rate, AscentRate::from_m_per_min(9.));
+        assert_eq!(rate.as_m_per_min(), 9.);
+    }
+}
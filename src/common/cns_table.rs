@@ -3,6 +3,18 @@ use std::ops::RangeInclusive;
 // (PO2 Range, slope, intercept)
 pub type CNSCoeffRow = (RangeInclusive<f64>, i32, i32);
 
+// ppO2 ranges non-empty, ascending, contiguous and non-overlapping
+pub(crate) fn is_valid_cns_table(cns_table: &[CNSCoeffRow]) -> bool {
+    let ranges_valid = cns_table
+        .iter()
+        .all(|(range, ..)| range.start().is_finite() && range.start() < range.end());
+    let ranges_contiguous = cns_table
+        .windows(2)
+        .all(|rows| rows[0].0.end() == rows[1].0.start());
+
+    !cns_table.is_empty() && ranges_valid && ranges_contiguous
+}
+
 pub const CNS_COEFFICIENTS: [CNSCoeffRow; 7] = [
     (0.5..=0.6, -1800, 1800),
     (0.6..=0.7, -1500, 1620),
@@ -2,11 +2,22 @@ use std::{cmp::Ordering, fmt};
 
 use crate::{DecoModel, Depth, DepthType, Gas, Time};
 
-use super::{DecoModelConfig, DiveState, MbarPressure, Sim};
+use super::{DecoModelConfig, DiveState, MbarPressure, Pressure, Sim, StopRounding};
 
 // @todo move to model config
-const DEFAULT_CEILING_WINDOW: DepthType = 3.;
+pub(crate) const DEFAULT_CEILING_WINDOW: DepthType = 3.;
 const DEFAULT_MAX_END_DEPTH: DepthType = 30.;
+// gas fractions tolerance when matching gas mixes
+const GAS_MIX_FRACTION_TOLERANCE: f64 = 1e-6;
+// O2 fractions tolerance of switch gases considered equivalent when preferring He washout
+const HE_WASHOUT_O2_FRACTION_TOLERANCE: f64 = 0.02;
+// severity score components half-saturation values (component score 0.5) and weights
+const SEVERITY_TTS_HALF_MINUTES: f64 = 20.;
+const SEVERITY_FIRST_STOP_HALF_METERS: f64 = 9.;
+const SEVERITY_STOPS_HALF_COUNT: f64 = 3.;
+const SEVERITY_WEIGHTS: (f64, f64, f64) = (0.5, 0.3, 0.2);
+// min ppO2 of gas considered breathable (hypoxic limit)
+pub(crate) const MIN_BREATHABLE_PP_O2: Pressure = 0.18;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum DecoAction {
@@ -16,14 +27,35 @@ enum DecoAction {
     Stop,
 }
 
+// outcome of a single deco simulation step
+#[derive(Clone, Debug, PartialEq)]
+enum DecoStep {
+    Stages(Vec<DecoStage>),
+    MissedStop,
+    Cleared,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DecoStageType {
     Ascent,
     DecoStop,
     GasSwitch,
+    // optional stop, not a deco obligation
+    SafetyStop,
+}
+
+// optional stop on no-deco dives deeper than required_above_depth
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SafetyStop {
+    pub depth: Depth,
+    pub time: Time,
+    pub required_above_depth: Depth,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DecoStage {
     pub stage_type: DecoStageType,
     pub start_depth: Depth,
@@ -32,14 +64,36 @@ pub struct DecoStage {
     pub gas: Gas,
 }
 
+// deco profile sample at fixed time resolution
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DecoSample {
+    pub time: Time,
+    pub depth: Depth,
+    pub gas: Gas,
+    pub ceiling: Depth,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Deco {
     deco_stages: Vec<DecoStage>,
     tts: Time,
     sim: bool,
+    first_stop_ceiling: Option<Depth>,
+    prefer_he_washout: bool,
+}
+
+// lazily calculated deco stages, simulation stepped only until the next stage is complete
+#[derive(Clone, Debug)]
+pub struct DecoIter<T: DecoModel + Clone + Sim> {
+    deco: Deco,
+    sim_model: T,
+    gas_mixes: Vec<Gas>,
+    yielded: usize,
+    done: bool,
 }
 
 #[derive(Debug, PartialEq, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DecoRuntime {
     // runtime
     pub deco_stages: Vec<DecoStage>,
@@ -51,6 +105,231 @@ pub struct DecoRuntime {
     pub tts_delta_at_5: Time,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecoDiff {
+    // TTS change (self - other)
+    pub tts_delta: Time,
+    // deco stops duration changes by stop depth, deepest first
+    pub stops: Vec<DecoStopDiff>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DecoStopDiff {
+    pub depth: Depth,
+    // total stop duration at depth in compared runtime (zero if no stop)
+    pub duration: Time,
+    // total stop duration at depth in other runtime (zero if no stop)
+    pub other_duration: Time,
+    // duration change (duration - other_duration)
+    pub duration_delta: Time,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GasPlanEntry {
+    pub gas: Gas,
+    // depth of switch to gas (zero for bottom gas)
+    pub switch_depth: Depth,
+    // total time breathing gas during deco runtime
+    pub total_time: Time,
+}
+
+// run table entry (stage end depth and absolute runtime at stage end)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RunListEntry {
+    pub stage_type: DecoStageType,
+    pub depth: Depth,
+    pub gas: Gas,
+    pub duration: Time,
+    pub run_time: Time,
+}
+
+// deco gas switch evaluation outcome for a gas mix at current depth
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GasSelectionOutcome {
+    Selected,          // next deco gas, switch at current depth
+    CurrentGas,        // gas currently breathed
+    TooLean,           // not more oxygen-rich than current gas
+    BelowMinPpO2,      // hypoxic at current depth
+    OverMod,           // current depth below gas MOD (deco ppO2 limit)
+    OverDensityLimit,  // denser than configured density limit at switch depth
+    OverEnd,           // END at current depth over max END
+    NotAtStopDepth,    // next deco gas, switching at deco stop depths only
+    OtherGasPreferred, // switch candidate, another gas selected as next deco gas
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GasSelectionReason {
+    pub gas: Gas,
+    pub outcome: GasSelectionOutcome,
+}
+
+impl fmt::Display for GasSelectionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GasSelectionOutcome::Selected => write!(f, "selected"),
+            GasSelectionOutcome::CurrentGas => write!(f, "current gas"),
+            GasSelectionOutcome::TooLean => write!(f, "too lean"),
+            GasSelectionOutcome::BelowMinPpO2 => write!(f, "below min ppO2"),
+            GasSelectionOutcome::OverMod => write!(f, "over MOD"),
+            GasSelectionOutcome::OverDensityLimit => write!(f, "over density limit"),
+            GasSelectionOutcome::OverEnd => write!(f, "over END"),
+            GasSelectionOutcome::NotAtStopDepth => write!(f, "not at stop depth"),
+            GasSelectionOutcome::OtherGasPreferred => write!(f, "other gas preferred"),
+        }
+    }
+}
+
+impl fmt::Display for DecoStageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecoStageType::Ascent => write!(f, "ascent"),
+            DecoStageType::DecoStop => write!(f, "deco_stop"),
+            DecoStageType::GasSwitch => write!(f, "gas_switch"),
+            DecoStageType::SafetyStop => write!(f, "safety_stop"),
+        }
+    }
+}
+
+impl DecoRuntime {
+    /// deco stages as CSV (stage_type,start_depth_m,end_depth_m,duration_s,gas)
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("stage_type,start_depth_m,end_depth_m,duration_s,gas\n");
+        for stage in self.deco_stages.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                stage.stage_type,
+                stage.start_depth.as_meters(),
+                stage.end_depth.as_meters(),
+                stage.duration.as_seconds(),
+                stage.gas
+            ));
+        }
+        csv
+    }
+
+    /// deco runtime as JSON
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// gases used in runtime in order of use with switch depths and total time on each gas
+    pub fn gas_plan(&self) -> Vec<GasPlanEntry> {
+        let mut gas_plan: Vec<GasPlanEntry> = vec![];
+        for stage in self.deco_stages.iter() {
+            let entry = match gas_plan.iter_mut().find(|entry| entry.gas == stage.gas) {
+                Some(entry) => entry,
+                None => {
+                    gas_plan.push(GasPlanEntry {
+                        gas: stage.gas,
+                        switch_depth: Depth::zero(),
+                        total_time: Time::zero(),
+                    });
+                    gas_plan.last_mut().unwrap()
+                }
+            };
+            if stage.stage_type == DecoStageType::GasSwitch {
+                entry.switch_depth = stage.start_depth;
+            }
+            entry.total_time += stage.duration;
+        }
+
+        gas_plan
+    }
+
+    /// run table with absolute runtime at the end of each stage, starting from given dive time
+    pub fn run_list(&self, start_time: Time) -> Vec<RunListEntry> {
+        let mut run_time = start_time;
+        self.deco_stages
+            .iter()
+            .map(|stage| {
+                run_time += stage.duration;
+                RunListEntry {
+                    stage_type: stage.stage_type,
+                    depth: stage.end_depth,
+                    gas: stage.gas,
+                    duration: stage.duration,
+                    run_time,
+                }
+            })
+            .collect()
+    }
+
+    /// deco obligation severity score in 0-1 range (e.g. for UI color-coding), 0 if no deco stops,
+    /// weighted sum of saturating components x / (x + x_half) for
+    /// TTS (weight 0.5, half at 20 min), first stop depth (0.3, 9m) and deco stops count (0.2, 3),
+    /// safety stops excluded from all components
+    pub fn severity(&self) -> f64 {
+        let deco_stops = self
+            .deco_stages
+            .iter()
+            .filter(|stage| stage.stage_type == DecoStageType::DecoStop);
+        let stops_count = deco_stops.clone().count() as f64;
+        let Some(first_stop) = deco_stops.clone().next() else {
+            return 0.;
+        };
+        let saturating = |x: f64, half: f64| x / (x + half);
+        let (tts_weight, first_stop_weight, stops_weight) = SEVERITY_WEIGHTS;
+        let tts = self
+            .deco_stages
+            .iter()
+            .filter(|stage| stage.stage_type == DecoStageType::SafetyStop)
+            .fold(self.tts, |tts, stage| tts.saturating_sub(stage.duration));
+
+        tts_weight * saturating(tts.as_minutes(), SEVERITY_TTS_HALF_MINUTES)
+            + first_stop_weight
+                * saturating(
+                    first_stop.start_depth.as_meters(),
+                    SEVERITY_FIRST_STOP_HALF_METERS,
+                )
+            + stops_weight * saturating(stops_count, SEVERITY_STOPS_HALF_COUNT)
+    }
+
+    /// TTS and deco stops changes compared to other runtime
+    pub fn diff(&self, other: &DecoRuntime) -> DecoDiff {
+        let mut stops: Vec<DecoStopDiff> = vec![];
+        let mut register_stops = |runtime: &DecoRuntime, is_other: bool| {
+            runtime
+                .deco_stages
+                .iter()
+                .filter(|stage| stage.stage_type == DecoStageType::DecoStop)
+                .for_each(|stage| {
+                    let stop_diff = match stops
+                        .iter_mut()
+                        .find(|stop_diff| stop_diff.depth == stage.start_depth)
+                    {
+                        Some(stop_diff) => stop_diff,
+                        None => {
+                            stops.push(DecoStopDiff {
+                                depth: stage.start_depth,
+                                duration: Time::zero(),
+                                other_duration: Time::zero(),
+                                duration_delta: Time::zero(),
+                            });
+                            stops.last_mut().unwrap()
+                        }
+                    };
+                    match is_other {
+                        true => stop_diff.other_duration += stage.duration,
+                        false => stop_diff.duration += stage.duration,
+                    }
+                });
+        };
+        register_stops(self, false);
+        register_stops(other, true);
+
+        stops.iter_mut().for_each(|stop_diff| {
+            stop_diff.duration_delta = stop_diff.duration - stop_diff.other_duration;
+        });
+        stops.sort_by(|a, b| b.depth.partial_cmp(&a.depth).unwrap());
+
+        DecoDiff {
+            tts_delta: self.tts - other.tts,
+            stops,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct MissedDecoStopViolation;
 
@@ -58,6 +337,8 @@ struct MissedDecoStopViolation;
 pub enum DecoCalculationError {
     EmptyGasList,
     CurrentGasNotInList,
+    NoBreathableGasAtStop { depth: Depth },
+    InvalidSamplingInterval { interval: Time },
 }
 
 impl fmt::Display for DecoCalculationError {
@@ -70,10 +351,46 @@ impl fmt::Display for DecoCalculationError {
                 f,
                 "Avaibalbe gas mixes must include current gas mix used by deco model"
             ),
+            DecoCalculationError::NoBreathableGasAtStop { depth } => write!(
+                f,
+                "None of available gas mixes breathable at required deco stop ({})",
+                depth
+            ),
+            DecoCalculationError::InvalidSamplingInterval { interval } => {
+                write!(f, "Invalid sampling interval [{}s]", interval.as_seconds())
+            }
         }
     }
 }
 
+impl<T: DecoModel + Clone + Sim> Iterator for DecoIter<T> {
+    type Item = Result<DecoStage, DecoCalculationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // stage complete once followed by another one (consecutive stages of the same type are merged)
+        while !self.done && self.deco.deco_stages.len() <= self.yielded + 1 {
+            match self
+                .deco
+                .next_deco_step(&mut self.sim_model, &self.gas_mixes)
+            {
+                Ok(DecoStep::Stages(deco_stages)) => deco_stages
+                    .into_iter()
+                    .for_each(|deco_stage| self.deco.register_deco_stage(deco_stage)),
+                Ok(DecoStep::MissedStop) => {}
+                Ok(DecoStep::Cleared) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    self.yielded = self.deco.deco_stages.len();
+                    return Some(Err(e));
+                }
+            }
+        }
+        let deco_stage = self.deco.deco_stages.get(self.yielded).copied()?;
+        self.yielded += 1;
+        Some(Ok(deco_stage))
+    }
+}
+
 impl Sim for Deco {
     fn fork(&self) -> Self {
         Self {
@@ -92,6 +409,30 @@ impl Deco {
         deco.fork()
     }
 
+    // precalculated ceiling (e.g. adaptive) limiting ceiling until first deco stop reached
+    pub(crate) fn with_first_stop_ceiling(mut self, first_stop_ceiling: Depth) -> Self {
+        self.first_stop_ceiling = Some(first_stop_ceiling);
+        self
+    }
+
+    /// deco stages iterator, calculated lazily one stage at a time (same stages as calc)
+    pub fn stages_iter<T: DecoModel + Clone + Sim>(
+        self,
+        deco_model: T,
+        gas_mixes: Vec<Gas>,
+    ) -> Result<DecoIter<T>, DecoCalculationError> {
+        Self::validate_gas_mixes(&deco_model, &gas_mixes)?;
+        let mut deco = self;
+        deco.prefer_he_washout = deco_model.config().prefer_he_washout();
+        Ok(DecoIter {
+            deco,
+            sim_model: deco_model,
+            gas_mixes,
+            yielded: 0,
+            done: false,
+        })
+    }
+
     pub fn calc<T: DecoModel + Clone + Sim>(
         &mut self,
         deco_model: T,
@@ -99,145 +440,19 @@ impl Deco {
     ) -> Result<DecoRuntime, DecoCalculationError> {
         // validate gas mixes
         Self::validate_gas_mixes(&deco_model, &gas_mixes)?;
+        self.prefer_he_washout = deco_model.config().prefer_he_washout();
 
         // run model simulation until no deco stages
         let mut sim_model: T = deco_model.clone();
-        let ascent_rate = sim_model.config().deco_ascent_rate();
         loop {
-            let DiveState {
-                depth: pre_stage_depth,
-                time: pre_stage_time,
-                gas: pre_stage_gas,
-                ..
-            } = sim_model.dive_state();
-            let ceiling = sim_model.ceiling();
-
-            // handle missed deco stop
-            // if missed deco stop, override sim model to depth at the expected stop and rerun the calculation
-            let next_deco_action = self.next_deco_action(&sim_model, gas_mixes.clone());
-            if let Err(e) = next_deco_action {
-                match e {
-                    MissedDecoStopViolation => {
-                        sim_model.record(
-                            self.deco_stop_depth(ceiling),
-                            Time::zero(),
-                            &pre_stage_gas,
-                        );
-                        return self.calc(sim_model, gas_mixes);
-                    }
-                }
+            match self.next_deco_step(&mut sim_model, &gas_mixes)? {
+                DecoStep::Stages(deco_stages) => deco_stages
+                    .into_iter()
+                    .for_each(|deco_stage| self.register_deco_stage(deco_stage)),
+                // sim model moved to the missed stop depth, rerun the calculation
+                DecoStep::MissedStop => return self.calc(sim_model, gas_mixes),
+                DecoStep::Cleared => break,
             }
-
-            // handle deco actions
-            let mut deco_stages: Vec<DecoStage> = vec![];
-            let (deco_action, next_switch_gas) = next_deco_action.unwrap();
-            match deco_action {
-                // deco obligation cleared
-                None => {
-                    break;
-                }
-
-                // handle mandatory deco stage
-                Some(deco_action) => {
-                    match deco_action {
-                        // ascent to min depth (deco stop or surface)
-                        DecoAction::AscentToCeil => {
-                            sim_model.record_travel_with_rate(
-                                self.deco_stop_depth(ceiling),
-                                ascent_rate,
-                                &pre_stage_gas,
-                            );
-                            let current_sim_state = sim_model.dive_state();
-                            let current_sim_time = current_sim_state.time;
-                            deco_stages.push(DecoStage {
-                                stage_type: DecoStageType::Ascent,
-                                start_depth: pre_stage_depth,
-                                end_depth: current_sim_state.depth,
-                                duration: current_sim_time - pre_stage_time,
-                                gas: current_sim_state.gas,
-                            })
-                        }
-
-                        // ascent to min depth with gas switch on next deco gas maximum operating depth
-                        DecoAction::AscentToGasSwitchDepth => {
-                            // @todo unwrap and handler err
-                            if let Some(next_switch_gas) = next_switch_gas {
-                                // travel to MOD
-                                let switch_gas_mod = next_switch_gas.max_operating_depth(1.6);
-                                sim_model.record_travel_with_rate(
-                                    switch_gas_mod,
-                                    ascent_rate,
-                                    &pre_stage_gas,
-                                );
-                                let DiveState {
-                                    depth: post_ascent_depth,
-                                    time: post_ascent_time,
-                                    ..
-                                } = sim_model.dive_state();
-                                deco_stages.push(DecoStage {
-                                    stage_type: DecoStageType::Ascent,
-                                    start_depth: pre_stage_depth,
-                                    end_depth: post_ascent_depth,
-                                    duration: post_ascent_time - pre_stage_time,
-                                    gas: pre_stage_gas,
-                                });
-
-                                // switch gas @todo configurable gas change duration
-                                sim_model.record(
-                                    sim_model.dive_state().depth,
-                                    Time::zero(),
-                                    &next_switch_gas,
-                                );
-                                // @todo configurable oxygen window stop
-                                let post_switch_state = sim_model.dive_state();
-                                deco_stages.push(DecoStage {
-                                    stage_type: DecoStageType::GasSwitch,
-                                    start_depth: post_ascent_depth,
-                                    end_depth: post_switch_state.depth,
-                                    duration: Time::zero(),
-                                    gas: next_switch_gas,
-                                });
-                            }
-                        }
-
-                        // switch gas without ascent
-                        DecoAction::SwitchGas => {
-                            let switch_gas = next_switch_gas.unwrap();
-                            // @todo configurable gas switch duration
-                            sim_model.record(pre_stage_depth, Time::zero(), &switch_gas);
-                            deco_stages.push(DecoStage {
-                                stage_type: DecoStageType::GasSwitch,
-                                start_depth: pre_stage_depth,
-                                end_depth: pre_stage_depth,
-                                duration: Time::zero(),
-                                gas: switch_gas,
-                            })
-                        }
-
-                        // decompression stop (a series of 1s segments, merged into one on cleared stop)
-                        DecoAction::Stop => {
-                            sim_model.record(
-                                pre_stage_depth,
-                                Time::from_seconds(1.),
-                                &pre_stage_gas,
-                            );
-                            let sim_state = sim_model.dive_state();
-                            // @todo dedupe here on deco instead of of add deco
-                            deco_stages.push(DecoStage {
-                                stage_type: DecoStageType::DecoStop,
-                                start_depth: pre_stage_depth,
-                                end_depth: sim_state.depth,
-                                duration: sim_state.time - pre_stage_time,
-                                gas: sim_state.gas,
-                            })
-                        }
-                    }
-                }
-            }
-            // register deco stages
-            deco_stages
-                .into_iter()
-                .for_each(|deco_stage| self.register_deco_stage(deco_stage));
         }
 
         let tts = self.tts;
@@ -267,6 +482,195 @@ impl Deco {
         })
     }
 
+    // single deco simulation step, deco stages of the step to be registered by the caller
+    fn next_deco_step<T: DecoModel + Clone>(
+        &self,
+        sim_model: &mut T,
+        gas_mixes: &[Gas],
+    ) -> Result<DecoStep, DecoCalculationError> {
+        let ascent_rate = sim_model.config().deco_ascent_rate();
+        let DiveState {
+            depth: pre_stage_depth,
+            time: pre_stage_time,
+            gas: pre_stage_gas,
+            ..
+        } = sim_model.dive_state();
+        let ceiling = self.deco_ceiling(sim_model);
+
+        // handle missed deco stop
+        // if missed deco stop, override sim model to depth at the expected stop
+        let next_deco_action = self.next_deco_action(sim_model, gas_mixes.to_vec());
+        if let Err(e) = next_deco_action {
+            match e {
+                MissedDecoStopViolation => {
+                    sim_model.record(
+                        self.next_stop_depth(sim_model, ceiling),
+                        Time::zero(),
+                        &pre_stage_gas,
+                    );
+                    return Ok(DecoStep::MissedStop);
+                }
+            }
+        }
+
+        // extend current deco stop to min stop time before leaving stop depth
+        if let Ok((deco_action, _)) = next_deco_action {
+            if deco_action != Some(DecoAction::Stop) {
+                if let Some(stop_extension) = self.pending_stop_extension(sim_model) {
+                    sim_model.record(pre_stage_depth, stop_extension, &pre_stage_gas);
+                    return Ok(DecoStep::Stages(vec![DecoStage {
+                        stage_type: DecoStageType::DecoStop,
+                        start_depth: pre_stage_depth,
+                        end_depth: pre_stage_depth,
+                        duration: stop_extension,
+                        gas: pre_stage_gas,
+                    }]));
+                }
+            }
+        }
+
+        // handle deco actions
+        let mut deco_stages: Vec<DecoStage> = vec![];
+        let (deco_action, next_switch_gas) = next_deco_action.unwrap();
+        match deco_action {
+            // deco obligation cleared
+            None => {
+                return Ok(DecoStep::Cleared);
+            }
+
+            // handle mandatory deco stage
+            Some(deco_action) => {
+                match deco_action {
+                    // ascent to min depth (deco stop or surface)
+                    DecoAction::AscentToCeil => {
+                        // safety stop instead of direct ascent to surface if no deco stops
+                        if let Some(safety_stop) = self.pending_safety_stop(sim_model, ceiling) {
+                            sim_model.record_travel_with_rate(
+                                safety_stop.depth,
+                                ascent_rate,
+                                &pre_stage_gas,
+                            );
+                            let DiveState {
+                                depth: post_ascent_depth,
+                                time: post_ascent_time,
+                                ..
+                            } = sim_model.dive_state();
+                            deco_stages.push(DecoStage {
+                                stage_type: DecoStageType::Ascent,
+                                start_depth: pre_stage_depth,
+                                end_depth: post_ascent_depth,
+                                duration: post_ascent_time.saturating_sub(pre_stage_time),
+                                gas: pre_stage_gas,
+                            });
+                            sim_model.record(post_ascent_depth, safety_stop.time, &pre_stage_gas);
+                            let post_stop_state = sim_model.dive_state();
+                            deco_stages.push(DecoStage {
+                                stage_type: DecoStageType::SafetyStop,
+                                start_depth: post_ascent_depth,
+                                end_depth: post_stop_state.depth,
+                                duration: post_stop_state.time.saturating_sub(post_ascent_time),
+                                gas: pre_stage_gas,
+                            });
+                            return Ok(DecoStep::Stages(deco_stages));
+                        }
+                        sim_model.record_travel_with_rate(
+                            self.next_stop_depth(sim_model, ceiling),
+                            ascent_rate,
+                            &pre_stage_gas,
+                        );
+                        let current_sim_state = sim_model.dive_state();
+                        let current_sim_time = current_sim_state.time;
+                        deco_stages.push(DecoStage {
+                            stage_type: DecoStageType::Ascent,
+                            start_depth: pre_stage_depth,
+                            end_depth: current_sim_state.depth,
+                            duration: current_sim_time.saturating_sub(pre_stage_time),
+                            gas: current_sim_state.gas,
+                        })
+                    }
+
+                    // ascent to min depth with gas switch on next deco gas maximum operating depth
+                    DecoAction::AscentToGasSwitchDepth => {
+                        // @todo unwrap and handler err
+                        if let Some(next_switch_gas) = next_switch_gas {
+                            // travel to MOD (or the stop above it if switching at stops only)
+                            let switch_depth = self.gas_switch_depth(sim_model, &next_switch_gas);
+                            sim_model.record_travel_with_rate(
+                                switch_depth,
+                                ascent_rate,
+                                &pre_stage_gas,
+                            );
+                            let DiveState {
+                                depth: post_ascent_depth,
+                                time: post_ascent_time,
+                                ..
+                            } = sim_model.dive_state();
+                            deco_stages.push(DecoStage {
+                                stage_type: DecoStageType::Ascent,
+                                start_depth: pre_stage_depth,
+                                end_depth: post_ascent_depth,
+                                duration: post_ascent_time.saturating_sub(pre_stage_time),
+                                gas: pre_stage_gas,
+                            });
+
+                            // switch gas @todo configurable gas change duration
+                            sim_model.record(
+                                sim_model.dive_state().depth,
+                                Time::zero(),
+                                &next_switch_gas,
+                            );
+                            let post_switch_state = sim_model.dive_state();
+                            deco_stages.push(DecoStage {
+                                stage_type: DecoStageType::GasSwitch,
+                                start_depth: post_ascent_depth,
+                                end_depth: post_switch_state.depth,
+                                duration: Time::zero(),
+                                gas: next_switch_gas,
+                            });
+                            deco_stages.extend(Self::oxygen_window_stop(sim_model));
+                        }
+                    }
+
+                    // switch gas without ascent
+                    DecoAction::SwitchGas => {
+                        let switch_gas = next_switch_gas.unwrap();
+                        // @todo configurable gas switch duration
+                        sim_model.record(pre_stage_depth, Time::zero(), &switch_gas);
+                        deco_stages.push(DecoStage {
+                            stage_type: DecoStageType::GasSwitch,
+                            start_depth: pre_stage_depth,
+                            end_depth: pre_stage_depth,
+                            duration: Time::zero(),
+                            gas: switch_gas,
+                        });
+                        deco_stages.extend(Self::oxygen_window_stop(sim_model));
+                    }
+
+                    // decompression stop (a series of segments in configured resolution, merged into one on cleared stop)
+                    DecoAction::Stop => {
+                        if !Self::breathable_gas_available(sim_model, pre_stage_depth, gas_mixes) {
+                            return Err(DecoCalculationError::NoBreathableGasAtStop {
+                                depth: pre_stage_depth,
+                            });
+                        }
+                        let stop_segment_time = self.stop_segment_time(sim_model, gas_mixes);
+                        sim_model.record(pre_stage_depth, stop_segment_time, &pre_stage_gas);
+                        let sim_state = sim_model.dive_state();
+                        // @todo dedupe here on deco instead of of add deco
+                        deco_stages.push(DecoStage {
+                            stage_type: DecoStageType::DecoStop,
+                            start_depth: pre_stage_depth,
+                            end_depth: sim_state.depth,
+                            duration: sim_state.time.saturating_sub(pre_stage_time),
+                            gas: sim_state.gas,
+                        })
+                    }
+                }
+            }
+        }
+        Ok(DecoStep::Stages(deco_stages))
+    }
+
     fn next_deco_action(
         &self,
         sim_model: &impl DecoModel,
@@ -278,31 +682,46 @@ impl Deco {
             ..
         } = sim_model.dive_state();
         let surface_pressure = sim_model.config().surface_pressure();
+        let gas_density_limit = sim_model.config().gas_density_limit();
+        let deco_pp_o2_limit = sim_model.config().deco_pp_o2_limit();
 
         // end deco simulation - surface
         if current_depth <= Depth::zero() {
             return Ok((None, None));
         }
 
-        let ceiling = sim_model.ceiling();
+        let ceiling = self.deco_ceiling(sim_model);
 
         match ceiling.partial_cmp(&Depth::zero()) {
             Some(Ordering::Equal | Ordering::Less) => Ok((Some(DecoAction::AscentToCeil), None)),
             Some(Ordering::Greater) => {
                 // check if deco violation
-                if current_depth < self.deco_stop_depth(ceiling) {
+                let stop_depth = self.next_stop_depth(sim_model, ceiling);
+                if !stop_depth.approx_le(current_depth) {
                     return Err(MissedDecoStopViolation);
                 }
 
-                let next_switch_gas =
-                    self.next_switch_gas(current_depth, &current_gas, gas_mixes, surface_pressure);
+                let next_switch_gas = self.next_switch_gas(
+                    current_depth,
+                    &current_gas,
+                    gas_mixes,
+                    surface_pressure,
+                    deco_pp_o2_limit,
+                    gas_density_limit,
+                );
                 // check if within mod @todo min operational depth
                 if let Some(switch_gas) = next_switch_gas {
                     //switch gas without ascent if within mod of next deco gas
-                    let gas_mod = switch_gas.max_operating_depth(1.6);
-                    let gas_end = switch_gas.equivalent_narcotic_depth(current_depth);
+                    let gas_mod = switch_gas.max_operating_depth(deco_pp_o2_limit);
+                    let gas_end = switch_gas.equivalent_narcotic_depth_with(
+                        current_depth,
+                        sim_model.config().o2_narcotic(),
+                    );
+                    let switch_allowed_at_depth = !sim_model.config().switch_at_stops_only()
+                        || self.is_stop_depth(current_depth);
                     if (switch_gas != current_gas)
-                        && (current_depth <= gas_mod)
+                        && switch_allowed_at_depth
+                        && current_depth.approx_le(gas_mod)
                         && (gas_end <= Depth::from_meters(DEFAULT_MAX_END_DEPTH))
                     {
                         return Ok((Some(DecoAction::SwitchGas), Some(switch_gas)));
@@ -311,12 +730,14 @@ impl Deco {
 
                 // check if within or below deco stop window
                 let ceiling_padding = current_depth - ceiling;
-                if ceiling_padding <= Depth::from_meters(DEFAULT_CEILING_WINDOW) {
+                if ceiling_padding.approx_le(Depth::from_meters(DEFAULT_CEILING_WINDOW))
+                    || current_depth.approx_le(stop_depth)
+                {
                     Ok((Some(DecoAction::Stop), None))
                 } else {
                     // ascent to next gas switch depth if next gas' MOD below ceiling
                     if let Some(next_switch_gas) = next_switch_gas {
-                        if next_switch_gas.max_operating_depth(1.6) >= ceiling {
+                        if self.gas_switch_depth(sim_model, &next_switch_gas) >= ceiling {
                             return Ok((
                                 Some(DecoAction::AscentToGasSwitchDepth),
                                 Some(next_switch_gas),
@@ -330,13 +751,74 @@ impl Deco {
         }
     }
 
+    // deco stop segment in configured resolution while stop still required after it,
+    // segment clearing the stop refined to 1s
+    fn stop_segment_time<T: DecoModel + Clone>(&self, sim_model: &T, gas_mixes: &[Gas]) -> Time {
+        let stop_resolution = sim_model.config().deco_stop_resolution();
+        let min_stop_resolution = Time::from_seconds(1.);
+        if stop_resolution <= min_stop_resolution {
+            return stop_resolution;
+        }
+        let DiveState { depth, gas, .. } = sim_model.dive_state();
+        let mut segment_sim_model = sim_model.clone();
+        segment_sim_model.record(depth, stop_resolution, &gas);
+        match self.next_deco_action(&segment_sim_model, gas_mixes.to_vec()) {
+            Ok((Some(DecoAction::Stop), _)) => stop_resolution,
+            _ => min_stop_resolution,
+        }
+    }
+
+    // configured stop at switch depth on new gas after gas switch
+    fn oxygen_window_stop(sim_model: &mut impl DecoModel) -> Option<DecoStage> {
+        let oxygen_window_stop = sim_model.config().oxygen_window_stop();
+        if oxygen_window_stop <= Time::zero() {
+            return None;
+        }
+        let DiveState { depth, gas, .. } = sim_model.dive_state();
+        sim_model.record(depth, oxygen_window_stop, &gas);
+        Some(DecoStage {
+            stage_type: DecoStageType::DecoStop,
+            start_depth: depth,
+            end_depth: depth,
+            duration: oxygen_window_stop,
+            gas,
+        })
+    }
+
+    // remaining time to min stop time if current deco stop shorter
+    fn pending_stop_extension(&self, sim_model: &impl DecoModel) -> Option<Time> {
+        let min_stop_time = sim_model.config().min_stop_time();
+        let last_stage = self.deco_stages.last()?;
+        if last_stage.stage_type != DecoStageType::DecoStop
+            || !last_stage.end_depth.approx_eq(sim_model.dive_state().depth)
+            || last_stage.duration >= min_stop_time
+        {
+            return None;
+        }
+
+        Some(min_stop_time - last_stage.duration)
+    }
+
+    // model ceiling, capped at first stop ceiling until first deco stop registered
+    fn deco_ceiling(&self, sim_model: &impl DecoModel) -> Depth {
+        match self.first_stop_ceiling {
+            Some(first_stop_ceiling) if !self.deco_stop_registered() => {
+                sim_model.ceiling().min(first_stop_ceiling)
+            }
+            _ => sim_model.ceiling(),
+        }
+    }
+
     /// check next deco gas in deco (the one with lowest MOD while more oxygen-rich than current)
+    /// gases denser than optional density limit (g/L) at switch depth are rejected
     fn next_switch_gas(
         &self,
         current_depth: Depth,
         current_gas: &Gas,
         gas_mixes: Vec<Gas>,
         surface_pressure: MbarPressure,
+        deco_pp_o2_limit: Pressure,
+        gas_density_limit: Option<f64>,
     ) -> Option<Gas> {
         let current_gas_partial_pressures =
             current_gas.partial_pressures(current_depth, surface_pressure);
@@ -345,7 +827,15 @@ impl Deco {
             .into_iter()
             .filter(|gas| {
                 let partial_pressures = gas.partial_pressures(current_depth, surface_pressure);
-                partial_pressures.o2 > current_gas_partial_pressures.o2
+                !gas.approx_eq(current_gas, GAS_MIX_FRACTION_TOLERANCE)
+                    && partial_pressures.o2 > current_gas_partial_pressures.o2
+            })
+            .filter(|gas| match gas_density_limit {
+                Some(density_limit) => {
+                    let switch_depth = current_depth.min(gas.max_operating_depth(deco_pp_o2_limit));
+                    gas.density(switch_depth, surface_pressure) <= density_limit
+                }
+                None => true,
             })
             .collect::<Vec<Gas>>();
 
@@ -357,10 +847,58 @@ impl Deco {
         });
 
         // mix with lowest MOD (by absolute o2 content)
-        switch_gasses.first().copied()
+        let switch_gas = switch_gasses.first().copied()?;
+        if !self.prefer_he_washout {
+            return Some(switch_gas);
+        }
+
+        // lowest He mix among mixes with O2 content equivalent to lowest MOD mix
+        let (switch_gas_o2, ..) = switch_gas.fractions();
+        switch_gasses
+            .into_iter()
+            .take_while(|gas| {
+                let (o2, ..) = gas.fractions();
+                o2 - switch_gas_o2 <= HE_WASHOUT_O2_FRACTION_TOLERANCE
+            })
+            .min_by(|a, b| {
+                let (_, a_he, ..) = a.fractions();
+                let (_, b_he, ..) = b.fractions();
+                a_he.partial_cmp(&b_he).unwrap()
+            })
+    }
+
+    // configured safety stop if ascending to surface without any deco stops after exceeding required depth
+    fn pending_safety_stop(
+        &self,
+        sim_model: &impl DecoModel,
+        ceiling: Depth,
+    ) -> Option<SafetyStop> {
+        let safety_stop = sim_model.config().safety_stop()?;
+        let DiveState {
+            depth, max_depth, ..
+        } = sim_model.dive_state();
+        let ascent_to_surface = self.deco_stop_depth(ceiling) <= Depth::zero();
+        let any_deco_stops = self
+            .deco_stages
+            .iter()
+            .any(|stage| stage.stage_type == DecoStageType::DecoStop);
+        if ascent_to_surface
+            && !any_deco_stops
+            && (max_depth > safety_stop.required_above_depth)
+            && (depth > safety_stop.depth)
+        {
+            return Some(safety_stop);
+        }
+
+        None
     }
 
     fn register_deco_stage(&mut self, stage: DecoStage) {
+        debug_assert!(
+            stage.duration >= Time::zero(),
+            "Negative deco stage duration [{}s]",
+            stage.duration.as_seconds()
+        );
         // dedupe iterative deco stops and merge into one
         let mut push_new = true;
         let last_stage = self.deco_stages.last_mut();
@@ -379,11 +917,58 @@ impl Deco {
         self.tts += stage.duration;
     }
 
+    // next stop depth, first stop rounded according to model config
+    fn next_stop_depth(&self, sim_model: &impl DecoModel, ceiling: Depth) -> Depth {
+        let stop_depth = self.deco_stop_depth(ceiling);
+        let first_stop = !self.deco_stop_registered();
+        if !first_stop || ceiling <= Depth::zero() {
+            return stop_depth;
+        }
+        let stop_window = Depth::from_meters(DEFAULT_CEILING_WINDOW);
+        match sim_model.config().first_stop_rounding() {
+            StopRounding::Up => stop_depth,
+            StopRounding::Nearest => ceiling.round_to(stop_window).max(stop_window),
+            StopRounding::ToGfLowDepth => match sim_model.gf_low_reference_depth() {
+                Some(gf_low_depth) => {
+                    let current_depth_stop = sim_model.dive_state().depth.floor_to(stop_window);
+                    gf_low_depth
+                        .ceil_to(stop_window)
+                        .min(current_depth_stop)
+                        .max(stop_depth)
+                }
+                None => stop_depth,
+            },
+        }
+    }
+
+    fn deco_stop_registered(&self) -> bool {
+        self.deco_stages
+            .iter()
+            .any(|stage| stage.stage_type == DecoStageType::DecoStop)
+    }
+
     // round ceiling up to the bottom of deco window
     fn deco_stop_depth(&self, ceiling: Depth) -> Depth {
-        Depth::from_meters(
-            DEFAULT_CEILING_WINDOW * (ceiling.as_meters() / DEFAULT_CEILING_WINDOW).ceil(),
-        )
+        Self::stop_depth(ceiling)
+    }
+
+    // deco stop depth for ceiling, rounded up to deco window
+    pub(crate) fn stop_depth(ceiling: Depth) -> Depth {
+        ceiling.ceil_to(Depth::from_meters(DEFAULT_CEILING_WINDOW))
+    }
+
+    fn is_stop_depth(&self, depth: Depth) -> bool {
+        depth.approx_eq(depth.round_to(Depth::from_meters(DEFAULT_CEILING_WINDOW)))
+    }
+
+    // depth to ascend to before switching to given gas
+    fn gas_switch_depth(&self, sim_model: &impl DecoModel, switch_gas: &Gas) -> Depth {
+        let config = sim_model.config();
+        let gas_mod = switch_gas.max_operating_depth(config.deco_pp_o2_limit());
+        match config.switch_at_stops_only() {
+            true => gas_mod.floor_to(Depth::from_meters(DEFAULT_CEILING_WINDOW)),
+            false => gas_mod,
+        }
     }
 
     fn validate_gas_mixes<T: DecoModel>(
@@ -394,12 +979,112 @@ impl Deco {
             return Err(DecoCalculationError::EmptyGasList);
         }
         let current_gas = deco_model.dive_state().gas;
-        let current_gas_in_available = gas_mixes.iter().find(|gas_mix| **gas_mix == current_gas);
-        if current_gas_in_available.is_none() {
+        if !Self::gas_in_mixes(&current_gas, gas_mixes) {
             return Err(DecoCalculationError::CurrentGasNotInList);
         }
         Ok(())
     }
+
+    // bottom ppO2 limit for bottom gas (leanest gas mix, never a deco gas switch candidate),
+    // deco ppO2 limit for deco gases
+    fn gas_pp_o2_limit(config: &impl DecoModelConfig, gas: &Gas, gas_mixes: &[Gas]) -> Pressure {
+        let (gas_o2, ..) = gas.fractions();
+        let is_bottom_gas = gas_mixes.iter().all(|gas_mix| {
+            let (gas_mix_o2, ..) = gas_mix.fractions();
+            gas_o2 <= gas_mix_o2 + GAS_MIX_FRACTION_TOLERANCE
+        });
+        match is_bottom_gas {
+            true => config.bottom_pp_o2_limit(),
+            false => config.deco_pp_o2_limit(),
+        }
+    }
+
+    // any gas mix within hypoxic limit and its ppO2 limit (bottom or deco gas) at depth
+    fn breathable_gas_available(
+        sim_model: &impl DecoModel,
+        depth: Depth,
+        gas_mixes: &[Gas],
+    ) -> bool {
+        let config = sim_model.config();
+        let surface_pressure = config.surface_pressure();
+        gas_mixes.iter().any(|gas| {
+            let pp_o2 = gas.partial_pressures(depth, surface_pressure).o2;
+            let pp_o2_limit = Self::gas_pp_o2_limit(&config, gas, gas_mixes);
+            (MIN_BREATHABLE_PP_O2..=pp_o2_limit).contains(&pp_o2)
+        })
+    }
+
+    /// deco gas switch evaluation of each gas mix at model's current depth and gas
+    /// (same criteria as deco calculation gas switches), in gas mixes order
+    pub fn explain_gas_selection(
+        deco_model: &impl DecoModel,
+        gas_mixes: Vec<Gas>,
+    ) -> Vec<GasSelectionReason> {
+        let config = deco_model.config();
+        let surface_pressure = config.surface_pressure();
+        let deco_pp_o2_limit = config.deco_pp_o2_limit();
+        let DiveState {
+            depth: current_depth,
+            gas: current_gas,
+            ..
+        } = deco_model.dive_state();
+        let current_pp_o2 = current_gas
+            .partial_pressures(current_depth, surface_pressure)
+            .o2;
+        let deco = Self {
+            prefer_he_washout: config.prefer_he_washout(),
+            ..Self::default()
+        };
+        let next_switch_gas = deco.next_switch_gas(
+            current_depth,
+            &current_gas,
+            gas_mixes.clone(),
+            surface_pressure,
+            deco_pp_o2_limit,
+            config.gas_density_limit(),
+        );
+        let switch_allowed_at_depth =
+            !config.switch_at_stops_only() || deco.is_stop_depth(current_depth);
+
+        gas_mixes
+            .into_iter()
+            .map(|gas| {
+                let pp_o2 = gas.partial_pressures(current_depth, surface_pressure).o2;
+                let gas_mod = gas.max_operating_depth(deco_pp_o2_limit);
+                let over_density_limit = config.gas_density_limit().is_some_and(|limit| {
+                    gas.density(current_depth.min(gas_mod), surface_pressure) > limit
+                });
+                let gas_end =
+                    gas.equivalent_narcotic_depth_with(current_depth, config.o2_narcotic());
+                let outcome = if gas.approx_eq(&current_gas, GAS_MIX_FRACTION_TOLERANCE) {
+                    GasSelectionOutcome::CurrentGas
+                } else if pp_o2 <= current_pp_o2 {
+                    GasSelectionOutcome::TooLean
+                } else if pp_o2 < MIN_BREATHABLE_PP_O2 {
+                    GasSelectionOutcome::BelowMinPpO2
+                } else if !current_depth.approx_le(gas_mod) {
+                    GasSelectionOutcome::OverMod
+                } else if over_density_limit {
+                    GasSelectionOutcome::OverDensityLimit
+                } else if gas_end > Depth::from_meters(DEFAULT_MAX_END_DEPTH) {
+                    GasSelectionOutcome::OverEnd
+                } else if next_switch_gas != Some(gas) {
+                    GasSelectionOutcome::OtherGasPreferred
+                } else if !switch_allowed_at_depth {
+                    GasSelectionOutcome::NotAtStopDepth
+                } else {
+                    GasSelectionOutcome::Selected
+                };
+                GasSelectionReason { gas, outcome }
+            })
+            .collect()
+    }
+
+    pub(crate) fn gas_in_mixes(gas: &Gas, gas_mixes: &[Gas]) -> bool {
+        gas_mixes
+            .iter()
+            .any(|gas_mix| gas_mix.approx_eq(gas, GAS_MIX_FRACTION_TOLERANCE))
+    }
 }
 
 #[cfg(test)]
@@ -458,11 +1143,65 @@ mod tests {
                 &current_gas,
                 available_gas_mixes,
                 1000,
+                1.6,
+                None,
             );
             assert_eq!(res, expected_switch_gas);
         }
     }
 
+    #[test]
+    fn test_next_switch_gas_density_limit() {
+        let tmx_15_55 = Gas::trimix(0.15, 0.55);
+        let ean_32 = Gas::ean32();
+        let tmx_32_25 = Gas::trimix(0.32, 0.25);
+        let gas_mixes = vec![tmx_15_55, ean_32, tmx_32_25];
+        let deco = Deco::default();
+        let depth = Depth::from_meters(40.);
+
+        let uncapped_switch_gas =
+            deco.next_switch_gas(depth, &tmx_15_55, gas_mixes.clone(), 1013, 1.6, None);
+        assert_eq!(uncapped_switch_gas, Some(ean_32));
+
+        let capped_switch_gas =
+            deco.next_switch_gas(depth, &tmx_15_55, gas_mixes, 1013, 1.6, Some(5.7));
+        assert_eq!(capped_switch_gas, Some(tmx_32_25));
+    }
+
+    #[test]
+    fn test_next_switch_gas_he_washout() {
+        let tmx_18_45 = Gas::trimix(0.18, 0.45);
+        let tmx_31_20 = Gas::trimix(0.31, 0.2);
+        let ean_32 = Gas::ean32();
+        let ean_50 = Gas::new(0.5, 0.);
+        let gas_mixes = vec![tmx_18_45, tmx_31_20, ean_32, ean_50];
+        let depth = Depth::from_meters(40.);
+
+        let deco = Deco::default();
+        let switch_gas =
+            deco.next_switch_gas(depth, &tmx_18_45, gas_mixes.clone(), 1013, 1.6, None);
+        assert_eq!(switch_gas, Some(tmx_31_20));
+
+        let he_washout_deco = Deco {
+            prefer_he_washout: true,
+            ..Deco::default()
+        };
+        let he_washout_switch_gas =
+            he_washout_deco.next_switch_gas(depth, &tmx_18_45, gas_mixes, 1013, 1.6, None);
+        assert_eq!(he_washout_switch_gas, Some(ean_32));
+
+        // no equivalent lower He mix
+        let he_washout_switch_gas = he_washout_deco.next_switch_gas(
+            depth,
+            &tmx_18_45,
+            vec![tmx_18_45, tmx_31_20, ean_50],
+            1013,
+            1.6,
+            None,
+        );
+        assert_eq!(he_washout_switch_gas, Some(tmx_31_20));
+    }
+
     #[test]
     fn should_err_on_empty_gas_mixes() {
         let mut deco = Deco::default();
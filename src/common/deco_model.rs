@@ -1,8 +1,11 @@
-use crate::common::deco::{DecoCalculationError, DecoRuntime};
-use crate::common::global_types::{CeilingType, MbarPressure};
+use crate::common::deco::{Deco, DecoCalculationError, DecoRuntime, DecoStageType, SafetyStop};
+use crate::common::global_types::{CeilingType, MbarPressure, StopRounding};
 use crate::common::ox_tox::OxTox;
-use crate::common::{AscentRatePerMinute, Cns, Gas, Otu};
-use crate::common::{Depth, Time};
+use crate::common::{AscentRatePerMinute, Cns, Gas, Otu, Pressure};
+use crate::common::{Depth, DepthType, Time};
+
+// max distance from stop depth considered at stop
+const AT_STOP_DEPTH_TOLERANCE: DepthType = 0.1;
 
 #[derive(Debug, PartialEq)]
 pub struct ConfigValidationErr {
@@ -25,11 +28,23 @@ pub trait DecoModelConfig {
     fn deco_ascent_rate(&self) -> AscentRatePerMinute;
     fn ceiling_type(&self) -> CeilingType;
     fn round_ceiling(&self) -> bool;
+    fn safety_stop(&self) -> Option<SafetyStop>;
+    fn first_stop_rounding(&self) -> StopRounding;
+    fn switch_at_stops_only(&self) -> bool;
+    fn prefer_he_washout(&self) -> bool;
+    fn o2_narcotic(&self) -> bool;
+    fn min_stop_time(&self) -> Time;
+    fn oxygen_window_stop(&self) -> Time;
+    fn deco_stop_resolution(&self) -> Time;
+    fn gas_density_limit(&self) -> Option<f64>;
+    fn deco_pp_o2_limit(&self) -> Pressure;
+    fn bottom_pp_o2_limit(&self) -> Pressure;
 }
 
 #[derive(Debug, Clone)]
 pub struct DiveState {
     pub depth: Depth,
+    pub max_depth: Depth,
     pub time: Time,
     pub gas: Gas,
     pub ox_tox: OxTox,
@@ -64,31 +79,86 @@ pub trait DecoModel {
         gas: &Gas,
     );
 
+    /// square profile: descent to bottom depth with given rate followed by bottom time at depth
+    fn record_square(
+        &mut self,
+        bottom_depth: Depth,
+        descent_rate: AscentRatePerMinute,
+        bottom_time: Time,
+        gas: &Gas,
+    ) {
+        self.record_travel_with_rate(bottom_depth, descent_rate, gas);
+        self.record(bottom_depth, bottom_time, gas);
+    }
+
+    /// surface interval breathing given gas (e.g. air or O2 between dives),
+    /// oxygen toxicity accrued or eliminated depending on surface ppO2
+    fn surface_interval(&mut self, time: Time, gas: &Gas) {
+        self.record(Depth::zero(), time, gas);
+    }
+
     /// current non decompression limit (NDL)
     fn ndl(&self) -> Time;
 
     /// current decompression ceiling in meters
     fn ceiling(&self) -> Depth;
 
+    /// direct ascent to surface within surface supersaturation limit (eg. GF high)
+    fn safe_to_surface(&self) -> bool;
+
     /// deco stages, TTL
     fn deco(&self, gas_mixes: Vec<Gas>) -> Result<DecoRuntime, DecoCalculationError>;
 
+    /// deco stages, TTL, current gas included as bottom gas if missing from gas mixes
+    fn deco_auto(&self, mut gas_mixes: Vec<Gas>) -> Result<DecoRuntime, DecoCalculationError> {
+        let current_gas = self.dive_state().gas;
+        if !Deco::gas_in_mixes(&current_gas, &gas_mixes) {
+            gas_mixes.insert(0, current_gas);
+        }
+        self.deco(gas_mixes)
+    }
+
     /// central nervous system oxygen toxicity
     fn cns(&self) -> Cns;
 
     /// pulmonary oxygen toxicity
     fn otu(&self) -> Otu;
 
+    /// depth at which GF low applies (first stop reference), if determined
+    fn gf_low_reference_depth(&self) -> Option<Depth> {
+        None
+    }
+
+    /// is current depth the mandatory deco stop depth (false if no deco stops)
+    fn at_deco_stop(&self, gas_mixes: Vec<Gas>) -> bool {
+        let Ok(runtime) = self.deco(gas_mixes) else {
+            return false;
+        };
+        let has_deco_stops = runtime
+            .deco_stages
+            .iter()
+            .any(|stage| stage.stage_type == DecoStageType::DecoStop);
+        if !has_deco_stops {
+            return false;
+        }
+        let stop_depth = Deco::stop_depth(self.ceiling());
+        (self.dive_state().depth - stop_depth).as_meters().abs() <= AT_STOP_DEPTH_TOLERANCE
+    }
+
     /// is in deco check
     fn in_deco(&self) -> bool {
         let ceiling_type = self.config().ceiling_type();
         match ceiling_type {
-            CeilingType::Actual => self.ceiling() > Depth::zero(),
+            CeilingType::Actual | CeilingType::CachedAdaptive | CeilingType::GfSurface => {
+                self.ceiling() > Depth::zero()
+            }
             CeilingType::Adaptive => {
                 let current_gas = self.dive_state().gas;
                 let runtime = self.deco(vec![current_gas]).unwrap();
-                let deco_stages = runtime.deco_stages;
-                deco_stages.len() > 1
+                runtime
+                    .deco_stages
+                    .iter()
+                    .any(|stage| stage.stage_type == DecoStageType::DecoStop)
             }
         }
     }
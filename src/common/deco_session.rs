@@ -0,0 +1,126 @@
+use crate::common::{
+    AscentRatePerMinute, Deco, DecoCalculationError, DecoModel, DecoRuntime, DecoStageType,
+    DiveState,
+};
+use crate::{Depth, Gas, Time};
+
+// last calculated runtime with model state it was calculated for
+#[derive(Clone, Debug)]
+struct CachedRuntime {
+    runtime: DecoRuntime,
+    depth: Depth,
+    ceiling: Depth,
+    gas: Gas,
+    time: Time,
+}
+
+/// incremental deco calculation for real-time use (e.g. dive computer updating every second),
+/// runtime recalculated only if depth, ceiling or gas changed beyond threshold since last calculation
+/// or ceiling moved to a different stop depth (e.g. entering deco)
+#[derive(Clone, Debug)]
+pub struct DecoSession<T: DecoModel> {
+    model: T,
+    gas_mixes: Vec<Gas>,
+    recalc_threshold: Depth,
+    cached: Option<CachedRuntime>,
+    recalculations: usize,
+}
+
+impl<T: DecoModel> DecoSession<T> {
+    pub fn new(model: T, gas_mixes: Vec<Gas>, recalc_threshold: Depth) -> Self {
+        Self {
+            model,
+            gas_mixes,
+            recalc_threshold,
+            cached: None,
+            recalculations: 0,
+        }
+    }
+
+    pub fn model(&self) -> &T {
+        &self.model
+    }
+
+    /// number of full deco recalculations performed
+    pub fn recalculations(&self) -> usize {
+        self.recalculations
+    }
+
+    pub fn record(&mut self, depth: Depth, time: Time, gas: &Gas) {
+        self.model.record(depth, time, gas);
+    }
+
+    pub fn record_travel_with_rate(
+        &mut self,
+        target_depth: Depth,
+        rate: AscentRatePerMinute,
+        gas: &Gas,
+    ) {
+        self.model.record_travel_with_rate(target_depth, rate, gas);
+    }
+
+    /// current deco runtime, cached runtime adjusted for elapsed time if no significant change
+    pub fn runtime(&mut self) -> Result<DecoRuntime, DecoCalculationError> {
+        let DiveState {
+            depth, time, gas, ..
+        } = self.model.dive_state();
+        let ceiling = self.model.ceiling();
+
+        if let Some(cached) = &self.cached {
+            if !self.requires_recalculation(cached, depth, ceiling, gas) {
+                let elapsed = time.saturating_sub(cached.time);
+                return Ok(Self::adjust_for_elapsed_time(&cached.runtime, elapsed));
+            }
+        }
+
+        let runtime = self.model.deco(self.gas_mixes.clone())?;
+        self.recalculations += 1;
+        self.cached = Some(CachedRuntime {
+            runtime: runtime.clone(),
+            depth,
+            ceiling,
+            gas,
+            time,
+        });
+
+        Ok(runtime)
+    }
+
+    fn requires_recalculation(
+        &self,
+        cached: &CachedRuntime,
+        depth: Depth,
+        ceiling: Depth,
+        gas: Gas,
+    ) -> bool {
+        let depth_delta = (depth - cached.depth).as_meters().abs();
+        let ceiling_delta = (ceiling - cached.ceiling).as_meters().abs();
+        let threshold = self.recalc_threshold.as_meters();
+        if depth_delta > threshold || ceiling_delta > threshold || gas != cached.gas {
+            return true;
+        }
+        // ceiling crossing zero or stop increment, first stop changed regardless of threshold
+        if !Deco::stop_depth(ceiling).approx_eq(Deco::stop_depth(cached.ceiling)) {
+            return true;
+        }
+        // current deco stop completed
+        let elapsed = self.model.dive_state().time.saturating_sub(cached.time);
+        match cached.runtime.deco_stages.first() {
+            Some(stage) if stage.stage_type == DecoStageType::DecoStop => elapsed >= stage.duration,
+            _ => false,
+        }
+    }
+
+    // time spent at current deco stop deducted from stop duration and TTS
+    fn adjust_for_elapsed_time(runtime: &DecoRuntime, elapsed: Time) -> DecoRuntime {
+        let mut adjusted_runtime = runtime.clone();
+        if let Some(stage) = adjusted_runtime.deco_stages.first_mut() {
+            if stage.stage_type == DecoStageType::DecoStop {
+                stage.duration = stage.duration.saturating_sub(elapsed);
+                adjusted_runtime.tts = adjusted_runtime.tts.saturating_sub(elapsed);
+            }
+        }
+
+        adjusted_runtime
+    }
+}
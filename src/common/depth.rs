@@ -1,7 +1,11 @@
 use core::fmt;
+use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, Mul, Sub};
 
-use super::DepthType;
+use super::{DepthType, MbarPressure, Pressure, SurfacePressure};
+
+// tolerance of depth comparisons in deco decisions (float jitter, e.g. from travel integration)
+pub(crate) const DEPTH_EPSILON: DepthType = 1e-6;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Units {
@@ -16,6 +20,7 @@ pub trait Unit<T = f64>: Sized {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Depth {
     m: DepthType,
 }
@@ -98,6 +103,18 @@ impl AddAssign for Depth {
     }
 }
 
+impl Sum for Depth {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, depth| acc + depth)
+    }
+}
+
+impl<'a> Sum<&'a Depth> for Depth {
+    fn sum<I: Iterator<Item = &'a Depth>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
 impl Unit for Depth {
     fn from_units(val: DepthType, units: Units) -> Self {
         match units {
@@ -131,9 +148,68 @@ impl Depth {
     pub fn as_meters(&self) -> DepthType {
         self.m
     }
+    /// absolute ambient pressure (bar) at depth given surface pressure
+    pub fn to_ambient_pressure(&self, surface_pressure: MbarPressure) -> Pressure {
+        SurfacePressure::from_mbar(surface_pressure).as_bar() + (self.m / 10.)
+    }
+    /// depth at absolute ambient pressure (bar) given surface pressure
+    pub fn from_ambient_pressure(
+        ambient_pressure: Pressure,
+        surface_pressure: MbarPressure,
+    ) -> Self {
+        Self {
+            m: (ambient_pressure - SurfacePressure::from_mbar(surface_pressure).as_bar()) * 10.,
+        }
+    }
     pub fn as_feet(&self) -> DepthType {
         Self::m_to_ft(self.m)
     }
+    /// round to nearest multiple of increment (half up)
+    pub fn round_to(&self, increment: Depth) -> Self {
+        Self {
+            m: increment.m * (self.m / increment.m).round(),
+        }
+    }
+    /// round up to multiple of increment
+    pub fn ceil_to(&self, increment: Depth) -> Self {
+        Self {
+            m: increment.m * (self.m / increment.m).ceil(),
+        }
+    }
+    /// round down to multiple of increment
+    pub fn floor_to(&self, increment: Depth) -> Self {
+        Self {
+            m: increment.m * (self.m / increment.m).floor(),
+        }
+    }
+    /// round up to full meters
+    pub fn ceil_m(&self) -> Self {
+        Self { m: self.m.ceil() }
+    }
+    /// round down to full meters
+    pub fn floor_m(&self) -> Self {
+        Self { m: self.m.floor() }
+    }
+    pub fn max(self, other: Self) -> Self {
+        match self < other {
+            true => other,
+            false => self,
+        }
+    }
+    pub fn min(self, other: Self) -> Self {
+        match self > other {
+            true => other,
+            false => self,
+        }
+    }
+    /// equal within depth epsilon (float jitter tolerant, exact `PartialEq` unchanged)
+    pub fn approx_eq(&self, other: Self) -> bool {
+        (self.m - other.m).abs() <= DEPTH_EPSILON
+    }
+    /// less than or equal within depth epsilon
+    pub fn approx_le(&self, other: Self) -> bool {
+        self.m - other.m <= DEPTH_EPSILON
+    }
     fn m_to_ft(m: DepthType) -> DepthType {
         m * 3.28084
     }
@@ -146,6 +222,16 @@ impl Depth {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sum() {
+        let depths = [Depth::from_meters(3.), Depth::from_meters(6.)];
+        assert_eq!(depths.iter().sum::<Depth>(), Depth::from_meters(9.));
+        assert_eq!(
+            Vec::<Depth>::new().into_iter().sum::<Depth>(),
+            Depth::zero()
+        );
+    }
+
     #[test]
     fn m_to_ft() {
         let depth = Depth::from_meters(1.);
@@ -169,6 +255,16 @@ mod tests {
         assert_eq!(with_precision(m, 5), 1.);
     }
 
+    #[test]
+    fn test_ambient_pressure() {
+        let depth = Depth::from_meters(10.);
+        let ambient_pressure = depth.to_ambient_pressure(1013);
+        assert_eq!(with_precision(ambient_pressure, 5), 2.013);
+        let round_trip_depth = Depth::from_ambient_pressure(ambient_pressure, 1013);
+        assert_eq!(with_precision(round_trip_depth.as_meters(), 5), 10.);
+        assert_eq!(Depth::zero().to_ambient_pressure(1013), 1.013);
+    }
+
     #[test]
     fn from_units_constructor() {
         let depth_m = Depth::from_units(1., Units::Metric);
@@ -180,6 +276,45 @@ mod tests {
         assert_eq!(depth_ft.as_meters(), 0.3048);
     }
 
+    #[test]
+    fn test_rounding() {
+        let depth = Depth::from_meters(7.1);
+        let increment = Depth::from_meters(3.);
+        assert_eq!(depth.round_to(increment), Depth::from_meters(6.));
+        assert_eq!(depth.floor_to(increment), Depth::from_meters(6.));
+        assert_eq!(depth.ceil_to(increment), Depth::from_meters(9.));
+        assert_eq!(
+            Depth::from_meters(7.5).round_to(increment),
+            Depth::from_meters(9.)
+        );
+        assert_eq!(depth.ceil_m(), Depth::from_meters(8.));
+        assert_eq!(depth.floor_m(), Depth::from_meters(7.));
+    }
+
+    #[test]
+    fn test_approx_comparisons() {
+        let depth = Depth::from_meters(21.);
+        let jittery_depth = Depth::from_meters(21. + 1e-9);
+        assert_ne!(jittery_depth, depth);
+        assert!(jittery_depth.approx_eq(depth));
+        assert!(depth.approx_eq(jittery_depth));
+        assert!(jittery_depth > depth);
+        assert!(jittery_depth.approx_le(depth));
+        assert!(depth.approx_le(jittery_depth));
+        assert!(!Depth::from_meters(21.1).approx_eq(depth));
+        assert!(!Depth::from_meters(21.1).approx_le(depth));
+    }
+
+    #[test]
+    fn test_min_max() {
+        let shallow = Depth::from_meters(3.);
+        let deep = Depth::from_meters(30.);
+        assert_eq!(shallow.max(deep), deep);
+        assert_eq!(deep.max(shallow), deep);
+        assert_eq!(shallow.min(deep), shallow);
+        assert_eq!(deep.min(shallow), shallow);
+    }
+
     fn with_precision(x: f64, precision: u32) -> f64 {
         let d = 10_u32.pow(precision) as f64;
         (x * d).round() / d
@@ -1,15 +1,28 @@
 use crate::common::global_types::{MbarPressure, Pressure};
 
-use super::Depth;
+use super::{Depth, SurfacePressure};
 
 // alveolar water vapor pressure assuming 47 mm Hg at 37C (Buehlmann's value)
-const ALVEOLI_WATER_VAPOR_PRESSURE: f64 = 0.0627;
+pub const ALVEOLI_WATER_VAPOR_PRESSURE: f64 = 0.0627;
+
+// max deviation of gas fractions sum from 1
+const GAS_FRACTIONS_SUM_EPSILON: f64 = 1e-9;
+
+// molar masses (g/mol)
+const O2_MOLAR_MASS: f64 = 31.998;
+const N2_MOLAR_MASS: f64 = 28.014;
+const HE_MOLAR_MASS: f64 = 4.003;
+const H2_MOLAR_MASS: f64 = 2.016;
+// ideal gas molar volume at 0C, 1 bar (L/mol)
+const MOLAR_VOLUME: f64 = 22.414;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gas {
     o2_pp: Pressure,
     n2_pp: Pressure,
     he_pp: Pressure,
+    h2_pp: Pressure,
 }
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -17,50 +30,132 @@ pub struct PartialPressures {
     pub o2: Pressure,
     pub n2: Pressure,
     pub he: Pressure,
+    pub h2: Pressure,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GasError {
+    InvalidO2(Pressure),
+    InvalidHe(Pressure),
+    InvalidH2(Pressure),
+    InvalidTotal,
+}
+
+impl std::fmt::Display for GasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasError::InvalidO2(o2_pp) => write!(f, "Invalid O2 partial pressure [{o2_pp}]"),
+            GasError::InvalidHe(he_pp) => write!(f, "Invalid He partial pressure [{he_pp}]"),
+            GasError::InvalidH2(h2_pp) => write!(f, "Invalid H2 partial pressure [{h2_pp}]"),
+            GasError::InvalidTotal => {
+                write!(f, "Invalid partial pressures, can't exceed 1ATA in total")
+            }
+        }
+    }
 }
 
 pub enum InertGas {
     Helium,
     Nitrogen,
+    Hydrogen,
 }
 
 impl std::fmt::Display for Gas {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:.0}/{:.0}", self.o2_pp * 100., self.he_pp * 100.)
+        write!(f, "{:.0}/{:.0}", self.o2_pp * 100., self.he_pp * 100.)?;
+        if self.h2_pp > 0. {
+            write!(f, "/{:.0}", self.h2_pp * 100.)?;
+        }
+        Ok(())
     }
 }
 
 impl Gas {
     /// init new gas with partial pressures (eg. 0.21, 0. for air)
     pub fn new(o2_pp: Pressure, he_pp: Pressure) -> Self {
+        Self::new_with_h2(o2_pp, he_pp, 0.)
+    }
+
+    /// init new gas with O2 and He percentages (eg. 32, 0 for EAN32)
+    pub fn from_percent(o2_pct: u8, he_pct: u8) -> Self {
+        Self::new(o2_pct as f64 / 100., he_pct as f64 / 100.)
+    }
+
+    /// init new gas with partial pressures including hydrogen (eg. 0.02, 0., 0.98 for hydrox)
+    pub fn new_with_h2(o2_pp: Pressure, he_pp: Pressure, h2_pp: Pressure) -> Self {
+        match Self::try_new_with_h2(o2_pp, he_pp, h2_pp) {
+            Ok(gas) => gas,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// fallible gas init with partial pressures (eg. 0.21, 0. for air)
+    pub fn try_new(o2_pp: Pressure, he_pp: Pressure) -> Result<Self, GasError> {
+        Self::try_new_with_h2(o2_pp, he_pp, 0.)
+    }
+
+    /// fallible gas init with partial pressures including hydrogen
+    pub fn try_new_with_h2(
+        o2_pp: Pressure,
+        he_pp: Pressure,
+        h2_pp: Pressure,
+    ) -> Result<Self, GasError> {
         if !(0. ..=1.).contains(&o2_pp) {
-            panic!("Invalid O2 partial pressure");
+            return Err(GasError::InvalidO2(o2_pp));
         }
         if !(0. ..=1.).contains(&he_pp) {
-            panic!("Invalid He partial pressure [{he_pp}]");
+            return Err(GasError::InvalidHe(he_pp));
         }
-        if (o2_pp + he_pp) > 1. {
-            panic!("Invalid partial pressures, can't exceed 1ATA in total");
+        if !(0. ..=1.).contains(&h2_pp) {
+            return Err(GasError::InvalidH2(h2_pp));
+        }
+        if (o2_pp + he_pp + h2_pp) > 1. {
+            return Err(GasError::InvalidTotal);
         }
 
-        Self {
+        // n2 as remainder rounded to 2 decimals, unrounded if rounding breaks fractions sum
+        let n2_remainder = 1. - (o2_pp + he_pp + h2_pp);
+        let mut n2_pp = (n2_remainder * 100.0).round() / 100.0;
+        if (n2_pp - n2_remainder).abs() > GAS_FRACTIONS_SUM_EPSILON {
+            n2_pp = n2_remainder;
+        }
+        let gas = Self {
             o2_pp,
             he_pp,
-            n2_pp: ((1. - (o2_pp + he_pp)) * 100.0).round() / 100.0,
-        }
+            h2_pp,
+            n2_pp,
+        };
+        debug_assert!(
+            (gas.o2_pp + gas.he_pp + gas.n2_pp + gas.h2_pp - 1.).abs() <= GAS_FRACTIONS_SUM_EPSILON
+        );
+
+        Ok(gas)
+    }
+
+    /// gas fractions (o2, he, n2, h2), summing to 1
+    pub fn fractions(&self) -> (f64, f64, f64, f64) {
+        (self.o2_pp, self.he_pp, self.n2_pp, self.h2_pp)
     }
 
     pub fn id(&self) -> String {
         self.to_string()
     }
 
+    /// gas equality within given fraction tolerance
+    pub fn approx_eq(&self, other: &Self, tolerance: f64) -> bool {
+        ((self.o2_pp - other.o2_pp).abs() <= tolerance)
+            && ((self.he_pp - other.he_pp).abs() <= tolerance)
+            && ((self.n2_pp - other.n2_pp).abs() <= tolerance)
+            && ((self.h2_pp - other.h2_pp).abs() <= tolerance)
+    }
+
     /// gas partial pressures
     pub fn partial_pressures(
         &self,
         depth: Depth,
         surface_pressure: MbarPressure,
     ) -> PartialPressures {
-        let gas_pressure = (surface_pressure as f64 / 1000.) + (depth.as_meters() / 10.);
+        let gas_pressure = depth.to_ambient_pressure(surface_pressure);
         self.gas_pressures_compound(gas_pressure)
     }
 
@@ -70,8 +165,21 @@ impl Gas {
         depth: Depth,
         surface_pressure: MbarPressure,
     ) -> PartialPressures {
-        let gas_pressure = ((surface_pressure as f64 / 1000.) + (depth.as_meters() / 10.))
-            - ALVEOLI_WATER_VAPOR_PRESSURE;
+        self.inspired_partial_pressures_with_water_vapor(
+            depth,
+            surface_pressure,
+            ALVEOLI_WATER_VAPOR_PRESSURE,
+        )
+    }
+
+    /// gas partial pressures in alveoli given custom alveolar water vapor pressure
+    pub fn inspired_partial_pressures_with_water_vapor(
+        &self,
+        depth: Depth,
+        surface_pressure: MbarPressure,
+        water_vapor_pressure: Pressure,
+    ) -> PartialPressures {
+        let gas_pressure = depth.to_ambient_pressure(surface_pressure) - water_vapor_pressure;
         self.gas_pressures_compound(gas_pressure)
     }
 
@@ -80,6 +188,7 @@ impl Gas {
             o2: self.o2_pp * gas_pressure,
             n2: self.n2_pp * gas_pressure,
             he: self.he_pp * gas_pressure,
+            h2: self.h2_pp * gas_pressure,
         }
     }
 
@@ -88,10 +197,18 @@ impl Gas {
         Depth::from_meters(10. * ((pp_o2_limit / self.o2_pp) - 1.))
     }
 
-    /// END
+    /// END (O2 considered narcotic)
     pub fn equivalent_narcotic_depth(&self, depth: Depth) -> Depth {
-        // @todo refactor
-        let mut end = (depth + Depth::from_meters(10.)) * Depth::from_meters(1. - self.he_pp)
+        self.equivalent_narcotic_depth_with(depth, true)
+    }
+
+    /// END, O2 optionally excluded from narcotic gases (narcotic potency relative to air's N2)
+    pub fn equivalent_narcotic_depth_with(&self, depth: Depth, o2_narcotic: bool) -> Depth {
+        let narcotic_fraction = match o2_narcotic {
+            true => 1. - self.he_pp,
+            false => (1. - self.he_pp - self.o2_pp) / Self::air().n2_pp,
+        };
+        let mut end = (depth + Depth::from_meters(10.)) * Depth::from_meters(narcotic_fraction)
             - Depth::from_meters(10.);
         if end < Depth::zero() {
             end = Depth::zero();
@@ -99,10 +216,68 @@ impl Gas {
         end
     }
 
-    // TODO standard nitrox (bottom and deco) and trimix gasses
+    /// EAD
+    pub fn equivalent_air_depth(&self, depth: Depth, surface_pressure: MbarPressure) -> Depth {
+        let air_n2_pp = Self::air().n2_pp;
+        let surface_depth =
+            Depth::from_meters(SurfacePressure::from_mbar(surface_pressure).as_bar() * 10.);
+        let mut ead = (depth + surface_depth) * (self.n2_pp / air_n2_pp) - surface_depth;
+        if ead < Depth::zero() {
+            ead = Depth::zero();
+        }
+        ead
+    }
+
+    /// gas density (g/L) at given depth
+    pub fn density(&self, depth: Depth, surface_pressure: MbarPressure) -> f64 {
+        let molar_mass = (self.o2_pp * O2_MOLAR_MASS)
+            + (self.n2_pp * N2_MOLAR_MASS)
+            + (self.he_pp * HE_MOLAR_MASS)
+            + (self.h2_pp * H2_MOLAR_MASS);
+        let ambient_pressure = depth.to_ambient_pressure(surface_pressure);
+        (molar_mass / MOLAR_VOLUME) * ambient_pressure
+    }
+
+    /// MOD and END at MOD for each gas given ppO2 limit
+    pub fn mod_end_table(gases: &[Gas], pp_o2_limit: Pressure) -> Vec<(Gas, Depth, Depth)> {
+        gases
+            .iter()
+            .map(|gas| {
+                let max_operating_depth = gas.max_operating_depth(pp_o2_limit);
+                let end = gas.equivalent_narcotic_depth(max_operating_depth);
+                (*gas, max_operating_depth, end)
+            })
+            .collect()
+    }
+
     pub fn air() -> Self {
         Self::new(0.21, 0.)
     }
+
+    pub fn ean32() -> Self {
+        Self::new(0.32, 0.)
+    }
+
+    pub fn ean36() -> Self {
+        Self::new(0.36, 0.)
+    }
+
+    pub fn ean50() -> Self {
+        Self::new(0.5, 0.)
+    }
+
+    pub fn oxygen() -> Self {
+        Self::new(1., 0.)
+    }
+
+    pub fn trimix(o2_pp: Pressure, he_pp: Pressure) -> Self {
+        Self::new(o2_pp, he_pp)
+    }
+
+    /// common deco gases set (EAN50, oxygen)
+    pub fn standard_deco_gases() -> Vec<Self> {
+        vec![Self::ean50(), Self::oxygen()]
+    }
 }
 
 #[cfg(test)]
@@ -143,6 +318,86 @@ mod tests {
         Gas::new(0.5, 0.51);
     }
 
+    #[test]
+    fn test_from_percent() {
+        assert_eq!(Gas::from_percent(50, 0), Gas::new(0.5, 0.));
+        assert_eq!(Gas::from_percent(21, 0), Gas::air());
+        assert_eq!(Gas::from_percent(18, 45), Gas::new(0.18, 0.45));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_percent_exceeding_100() {
+        Gas::from_percent(60, 50);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_percent_invalid_o2() {
+        Gas::from_percent(101, 0);
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(Gas::try_new(0.21, 0.), Ok(Gas::air()));
+        assert_eq!(Gas::try_new(1.1, 0.), Err(GasError::InvalidO2(1.1)));
+        assert_eq!(Gas::try_new(0.21, -0.1), Err(GasError::InvalidHe(-0.1)));
+        assert_eq!(Gas::try_new(0.5, 0.51), Err(GasError::InvalidTotal));
+        assert_eq!(
+            Gas::try_new_with_h2(0.2, 0.5, 0.31),
+            Err(GasError::InvalidTotal)
+        );
+        assert_eq!(
+            GasError::InvalidO2(1.1).to_string(),
+            "Invalid O2 partial pressure [1.1]"
+        );
+    }
+
+    #[test]
+    fn test_fractions_sum() {
+        let test_cases = [
+            (0.21, 0.),
+            (0.32, 0.),
+            (0.325, 0.),
+            (0.33, 0.33),
+            (0.18, 0.45),
+            (0.105, 0.705),
+            (0.1, 0.9),
+            (1., 0.),
+        ];
+        for (o2, he) in test_cases {
+            let gas = Gas::new(o2, he);
+            let (o2_fraction, he_fraction, n2_fraction, h2_fraction) = gas.fractions();
+            assert_eq!((o2_fraction, he_fraction, h2_fraction), (o2, he, 0.));
+            assert!((o2_fraction + he_fraction + n2_fraction - 1.).abs() < 1e-9);
+        }
+        assert_eq!(Gas::air().fractions(), (0.21, 0., 0.79, 0.));
+
+        // hydrogen mixes
+        for (o2, he, h2) in [(0.02, 0., 0.98), (0.04, 0.5, 0.3), (0.033, 0.333, 0.333)] {
+            let gas = Gas::new_with_h2(o2, he, h2);
+            let (o2_fraction, he_fraction, n2_fraction, h2_fraction) = gas.fractions();
+            assert_eq!((o2_fraction, he_fraction, h2_fraction), (o2, he, h2));
+            assert!((o2_fraction + he_fraction + n2_fraction + h2_fraction - 1.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_valid_gas_hydreliox() {
+        let hydreliox = Gas::new_with_h2(0.04, 0.5, 0.3);
+        assert_eq!(hydreliox.o2_pp, 0.04);
+        assert_eq!(hydreliox.he_pp, 0.5);
+        assert_eq!(hydreliox.h2_pp, 0.3);
+        assert_eq!(hydreliox.n2_pp, 0.16);
+        assert_eq!(hydreliox.id(), "4/50/30");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_partial_pressures_with_h2() {
+        Gas::new_with_h2(0.2, 0.5, 0.31);
+    }
+
     #[test]
     fn test_partial_pressures_air() {
         let air = Gas::new(0.21, 0.);
@@ -152,7 +407,8 @@ mod tests {
             PartialPressures {
                 o2: 0.42,
                 n2: 1.58,
-                he: 0.
+                he: 0.,
+                h2: 0.,
             }
         );
     }
@@ -166,7 +422,8 @@ mod tests {
             PartialPressures {
                 o2: 0.42,
                 he: 0.70,
-                n2: 0.88
+                n2: 0.88,
+                h2: 0.,
             }
         )
     }
@@ -181,11 +438,29 @@ mod tests {
             PartialPressures {
                 o2: 0.406833,
                 n2: 1.530467,
-                he: 0.0
+                he: 0.0,
+                h2: 0.0,
             }
         );
     }
 
+    #[test]
+    fn test_inspired_partial_pressures_with_water_vapor() {
+        let air = Gas::new(0.21, 0.);
+        let depth = Depth::from_meters(10.);
+        assert_eq!(
+            air.inspired_partial_pressures_with_water_vapor(
+                depth,
+                1000,
+                ALVEOLI_WATER_VAPOR_PRESSURE
+            ),
+            air.inspired_partial_pressures(depth, 1000)
+        );
+        let schreiner_pressures =
+            air.inspired_partial_pressures_with_water_vapor(depth, 1000, 0.0493);
+        assert!(schreiner_pressures.n2 > air.inspired_partial_pressures(depth, 1000).n2);
+    }
+
     #[test]
     fn test_mod() {
         // o2, he, max_ppo2, MOD
@@ -202,6 +477,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_density() {
+        let depth = Depth::from_meters(50.);
+        let air_density = Gas::air().density(depth, 1013);
+        let tmx_density = Gas::trimix(0.21, 0.35).density(depth, 1013);
+        assert!(air_density > 5.7);
+        assert!(tmx_density < 5.7);
+        assert_eq!(Gas::air().density(Depth::zero(), 1000), 1.2871705184259838);
+    }
+
+    #[test]
+    fn test_mod_end_table() {
+        let gases = [Gas::air(), Gas::ean50(), Gas::oxygen()];
+        let deco_table = Gas::mod_end_table(&gases, 1.6);
+        assert_eq!(deco_table.len(), 3);
+        let (gas, o2_mod, o2_end) = deco_table[2];
+        assert_eq!(gas, Gas::oxygen());
+        assert!((o2_mod.as_meters() - 6.).abs() < 1e-9);
+        assert!((o2_end.as_meters() - 6.).abs() < 1e-9);
+        assert_eq!(deco_table[1].1, Depth::from_meters(22.));
+
+        let bottom_table = Gas::mod_end_table(&gases, 1.4);
+        let (gas, air_mod, air_end) = bottom_table[0];
+        assert_eq!(gas, Gas::air());
+        assert_eq!(air_mod, Depth::from_meters(56.66666666666666));
+        assert_eq!(air_end, air_mod);
+    }
+
     #[test]
     fn test_end() {
         // depth, o2, he, END
@@ -217,6 +520,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_end_o2_non_narcotic() {
+        let ean32 = Gas::ean32();
+        let depth = Depth::from_meters(30.);
+        let end_o2_narcotic = ean32.equivalent_narcotic_depth_with(depth, true);
+        let end_o2_non_narcotic = ean32.equivalent_narcotic_depth_with(depth, false);
+        assert_eq!(end_o2_narcotic, ean32.equivalent_narcotic_depth(depth));
+        assert_eq!(end_o2_narcotic, depth);
+        assert!(end_o2_non_narcotic < end_o2_narcotic);
+        // air END equal to depth regardless of O2 narcosis
+        assert_eq!(
+            Gas::air().equivalent_narcotic_depth_with(depth, false),
+            depth
+        );
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let air = Gas::air();
+        let air_from_sum = Gas::new(0.1 + 0.11, 0.);
+        assert_ne!(air, air_from_sum);
+        assert!(air.approx_eq(&air_from_sum, 1e-9));
+        assert!(!air.approx_eq(&Gas::new(0.22, 0.), 1e-9));
+        assert!(!air.approx_eq(&Gas::new(0.21, 0.01), 1e-9));
+    }
+
+    #[test]
+    fn test_ead() {
+        // depth, o2, surface pressure, EAD
+        let test_cases = [
+            (30., 0.32, 1000, 24.43037974683544),
+            (30., 0.21, 1013, 30.),
+            (0., 0.5, 1013, 0.),
+        ];
+        for (depth, o2_pp, surface_pressure, expected_ead) in test_cases {
+            let nitrox = Gas::new(o2_pp, 0.);
+            let calculated_ead =
+                nitrox.equivalent_air_depth(Depth::from_meters(depth), surface_pressure);
+            assert_eq!(
+                (calculated_ead.as_meters() * 1e9).round(),
+                (expected_ead * 1e9_f64).round()
+            );
+        }
+    }
+
+    #[test]
+    fn test_standard_gases() {
+        assert_eq!(Gas::ean32(), Gas::new(0.32, 0.));
+        assert_eq!(Gas::ean36(), Gas::new(0.36, 0.));
+        assert_eq!(Gas::ean50(), Gas::new(0.5, 0.));
+        assert_eq!(Gas::oxygen(), Gas::new(1., 0.));
+        assert_eq!(Gas::trimix(0.18, 0.45), Gas::new(0.18, 0.45));
+
+        let deco_gases = Gas::standard_deco_gases();
+        assert_eq!(deco_gases.len(), 2);
+        assert!(deco_gases.contains(&Gas::ean50()));
+        assert!(deco_gases.contains(&Gas::oxygen()));
+    }
+
     #[test]
     fn test_id() {
         let ean32 = Gas::new(0.32, 0.);
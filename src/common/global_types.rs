@@ -14,7 +14,42 @@ pub enum NDLType {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CeilingType {
     Actual,
     Adaptive,
+    CachedAdaptive, // actual ceiling, adaptive ceiling calculated once per deco runtime for first stop
+    GfSurface, // ceiling at GF high without GF low slope, cleared once surfacing GF within GF high
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopRounding {
+    Nearest,      // nearest stop increment, never at the surface
+    Up,           // next deeper stop increment
+    ToGfLowDepth, // stop increment at or below GF low depth
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GfLowReference {
+    MaxSupersaturation, // depth at which leading tissue reaches GF low
+    FirstStop,          // first deco stop, GF low depth rounded up to stop increment
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Conservatism {
+    Low,    // GF 40/85
+    Medium, // GF 35/75
+    High,   // GF 30/70
+}
+
+impl Conservatism {
+    pub fn gradient_factors(&self) -> GradientFactors {
+        match self {
+            Conservatism::Low => (40, 85),
+            Conservatism::Medium => (35, 75),
+            Conservatism::High => (30, 70),
+        }
+    }
 }
@@ -1,25 +1,40 @@
+mod ascent_rate;
 mod cns_table;
 mod deco;
 mod deco_model;
+mod deco_session;
 mod depth;
 mod gas;
 mod global_types;
 mod ox_tox;
 mod record;
 mod sim;
+mod sticky_deco_plan;
+mod surface_pressure;
 mod time;
 
+pub use ascent_rate::AscentRate;
+pub(crate) use cns_table::is_valid_cns_table;
 pub use cns_table::{CNSCoeffRow, CNS_COEFFICIENTS};
-pub use deco::{Deco, DecoCalculationError, DecoRuntime, DecoStage, DecoStageType};
+pub use deco::{
+    Deco, DecoCalculationError, DecoDiff, DecoIter, DecoRuntime, DecoSample, DecoStage,
+    DecoStageType, DecoStopDiff, GasPlanEntry, GasSelectionOutcome, GasSelectionReason,
+    RunListEntry, SafetyStop,
+};
+pub(crate) use deco::{DEFAULT_CEILING_WINDOW, MIN_BREATHABLE_PP_O2};
 pub use deco_model::{ConfigValidationErr, DecoModel, DecoModelConfig, DiveState};
+pub use deco_session::DecoSession;
 pub use depth::{Depth, Unit, Units};
 pub use time::Time;
 
-pub use gas::{Gas, InertGas, PartialPressures};
+pub use gas::{Gas, GasError, InertGas, PartialPressures, ALVEOLI_WATER_VAPOR_PRESSURE};
 pub use global_types::{
-    AscentRatePerMinute, CeilingType, Cns, DepthType, GradientFactor, GradientFactors,
-    MbarPressure, NDLType, Otu, Pressure,
+    AscentRatePerMinute, CeilingType, Cns, Conservatism, DepthType, GfLowReference, GradientFactor,
+    GradientFactors, MbarPressure, NDLType, Otu, Pressure, StopRounding,
 };
 pub use ox_tox::OxTox;
-pub use record::RecordData;
+pub(crate) use ox_tox::CNS_LIMIT_OVER_MAX_PP02_SECONDS;
+pub use record::{ProfileSegment, RecordData, RecordError};
 pub use sim::Sim;
+pub use sticky_deco_plan::StickyDecoPlan;
+pub use surface_pressure::SurfacePressure;
@@ -1,16 +1,17 @@
 use std::cmp::Ordering;
 
 use crate::common::CNS_COEFFICIENTS;
-use crate::{Pressure, RecordData};
+use crate::{Pressure, RecordData, Time};
 
 use super::global_types::Otu;
 use super::{CNSCoeffRow, Cns, Depth, MbarPressure};
 
 const CNS_ELIMINATION_HALF_TIME_MINUTES: f64 = 90.;
-const CNS_LIMIT_OVER_MAX_PP02_SECONDS: f64 = 400.;
+pub(crate) const CNS_LIMIT_OVER_MAX_PP02_SECONDS: f64 = 400.;
 const OTU_EQUATION_EXPONENT: f64 = -0.8333;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OxTox {
     cns: Cns,
     otu: Otu,
@@ -32,17 +33,58 @@ impl OxTox {
     }
 
     pub fn recalculate(&mut self, record: &RecordData, surface_pressure: MbarPressure) {
-        self.recalculate_cns(record, surface_pressure);
+        self.recalculate_with_cns_limit(
+            record,
+            surface_pressure,
+            Time::from_seconds(CNS_LIMIT_OVER_MAX_PP02_SECONDS),
+        );
+    }
+
+    /// recalculate given custom CNS time limit for ppO2 over 1.6
+    pub fn recalculate_with_cns_limit(
+        &mut self,
+        record: &RecordData,
+        surface_pressure: MbarPressure,
+        cns_limit_over_max_pp_o2: Time,
+    ) {
+        self.recalculate_with_cns_table(
+            record,
+            surface_pressure,
+            cns_limit_over_max_pp_o2,
+            &CNS_COEFFICIENTS,
+        );
+    }
+
+    /// recalculate given custom CNS time limit for ppO2 over 1.6 and custom CNS coefficients table
+    pub fn recalculate_with_cns_table(
+        &mut self,
+        record: &RecordData,
+        surface_pressure: MbarPressure,
+        cns_limit_over_max_pp_o2: Time,
+        cns_table: &[CNSCoeffRow],
+    ) {
+        self.recalculate_cns(
+            record,
+            surface_pressure,
+            cns_limit_over_max_pp_o2,
+            cns_table,
+        );
         self.recalculate_otu(record, surface_pressure);
     }
 
-    fn recalculate_cns(&mut self, record: &RecordData, surface_pressure: MbarPressure) {
+    fn recalculate_cns(
+        &mut self,
+        record: &RecordData,
+        surface_pressure: MbarPressure,
+        cns_limit_over_max_pp_o2: Time,
+        cns_table: &[CNSCoeffRow],
+    ) {
         let RecordData { depth, time, gas } = *record;
 
         let pp_o2 = gas.inspired_partial_pressures(depth, surface_pressure).o2;
 
         // attempt to assign CNS coefficients by o2 partial pressure
-        let coeffs_for_range = self.assign_cns_coeffs(pp_o2);
+        let coeffs_for_range = self.assign_cns_coeffs(pp_o2, cns_table);
         // only calculate CNS change if o2 partial pressure higher than 0.5
         if let Some((.., slope, intercept)) = coeffs_for_range {
             // time limit for given P02
@@ -55,7 +97,7 @@ impl OxTox {
                 self.cns /= 2_f64.powf(time.as_minutes() / (CNS_ELIMINATION_HALF_TIME_MINUTES));
             } else if pp_o2 > 1.6 {
                 // increase CNS by a constant when ppO2 higher than 1.6
-                self.cns += (time.as_seconds() / CNS_LIMIT_OVER_MAX_PP02_SECONDS) * 100.;
+                self.cns += (time.as_seconds() / cns_limit_over_max_pp_o2.as_seconds()) * 100.;
             }
         }
     }
@@ -74,9 +116,9 @@ impl OxTox {
     }
 
     // find CNS coefficients by o2 partial pressure
-    fn assign_cns_coeffs(&self, pp_o2: Pressure) -> Option<CNSCoeffRow> {
+    fn assign_cns_coeffs(&self, pp_o2: Pressure, cns_table: &[CNSCoeffRow]) -> Option<CNSCoeffRow> {
         let mut coeffs_for_range: Option<CNSCoeffRow> = None;
-        for row in CNS_COEFFICIENTS.into_iter() {
+        for row in cns_table.iter().cloned() {
             let row_range = row.0.clone();
             let in_range_start_exclusive =
                 (&pp_o2 != row_range.start()) && row_range.contains(&pp_o2);
@@ -93,7 +135,11 @@ impl OxTox {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Gas, Time};
+    use crate::Gas;
+
+    fn default_over_max_limit() -> Time {
+        Time::from_seconds(CNS_LIMIT_OVER_MAX_PP02_SECONDS)
+    }
 
     #[test]
     fn test_default() {
@@ -116,7 +162,7 @@ mod tests {
         ];
 
         for (pp_o2, is_assignable) in assignable_cases.into_iter() {
-            let row = ox_tox.assign_cns_coeffs(pp_o2);
+            let row = ox_tox.assign_cns_coeffs(pp_o2, &CNS_COEFFICIENTS);
             if is_assignable {
                 assert!(row
                     .unwrap_or_else(|| panic!("row for ppO2 {} not found", pp_o2))
@@ -142,7 +188,7 @@ mod tests {
             gas: &ean_32,
         };
 
-        ox_tox.recalculate_cns(&record, 1013);
+        ox_tox.recalculate_cns(&record, 1013, default_over_max_limit(), &CNS_COEFFICIENTS);
         assert_eq!(ox_tox.cns(), 15.018262206843517);
     }
 
@@ -155,7 +201,7 @@ mod tests {
             time: Time::from_minutes(75.),
             gas: &Gas::new(0.35, 0.),
         };
-        ox_tox.recalculate_cns(&record, 1013);
+        ox_tox.recalculate_cns(&record, 1013, default_over_max_limit(), &CNS_COEFFICIENTS);
         assert_eq!(ox_tox.cns, 48.31898259550245);
         // 2x 90 mins half time
         let mut i = 0;
@@ -167,6 +213,8 @@ mod tests {
                     gas: &Gas::air(),
                 },
                 1013,
+                default_over_max_limit(),
+                &CNS_COEFFICIENTS,
             );
             i += 1;
         }
@@ -181,7 +229,19 @@ mod tests {
             time: Time::from_seconds(400.),
             gas: &Gas::new(0.5, 0.),
         };
-        ox_tox.recalculate_cns(&record, 1013);
+        ox_tox.recalculate_cns(&record, 1013, default_over_max_limit(), &CNS_COEFFICIENTS);
+        assert_eq!(ox_tox.cns(), 100.)
+    }
+
+    #[test]
+    fn test_cns_above_max_ppo2_custom_limit() {
+        let mut ox_tox = OxTox::default();
+        let record = RecordData {
+            depth: Depth::from_meters(30.),
+            time: Time::from_seconds(200.),
+            gas: &Gas::new(0.5, 0.),
+        };
+        ox_tox.recalculate_cns(&record, 1013, Time::from_seconds(200.), &CNS_COEFFICIENTS);
         assert_eq!(ox_tox.cns(), 100.)
     }
 
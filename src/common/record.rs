@@ -1,4 +1,6 @@
-use crate::common::{Depth, Gas, Time};
+use std::fmt;
+
+use crate::common::{Depth, Gas, Pressure, Time};
 
 #[derive(Debug)]
 pub struct RecordData<'a> {
@@ -6,3 +8,45 @@ pub struct RecordData<'a> {
     pub time: Time,
     pub gas: &'a Gas,
 }
+
+// linear travel to depth in given time on gas (constant depth if unchanged)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ProfileSegment {
+    pub depth: Depth,
+    pub time: Time,
+    pub gas: Gas,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RecordError {
+    InvalidDepth(Depth),
+    // depth beyond configured max depth of model validity
+    DepthExceedsModelLimit(Depth),
+    TimestampInPast(Time),
+    // gas ppO2 at depth exceeding configured limit
+    PpO2LimitExceeded(Pressure),
+    // gas ppO2 at depth below min breathable ppO2 (hypoxic)
+    PpO2BelowMin(Pressure),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::InvalidDepth(depth) => write!(f, "Invalid depth [{}]", depth),
+            RecordError::DepthExceedsModelLimit(depth) => {
+                write!(f, "Depth [{}] exceeds model max depth", depth)
+            }
+            RecordError::TimestampInPast(timestamp) => write!(
+                f,
+                "Timestamp [{}s] earlier than current model time",
+                timestamp.as_seconds()
+            ),
+            RecordError::PpO2LimitExceeded(pp_o2) => {
+                write!(f, "Gas ppO2 [{}] exceeds configured limit", pp_o2)
+            }
+            RecordError::PpO2BelowMin(pp_o2) => {
+                write!(f, "Gas ppO2 [{}] below min breathable ppO2", pp_o2)
+            }
+        }
+    }
+}
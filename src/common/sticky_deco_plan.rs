@@ -0,0 +1,159 @@
+use crate::common::{
+    AscentRatePerMinute, DecoCalculationError, DecoModel, DecoRuntime, DecoStage, DecoStageType,
+    DEFAULT_CEILING_WINDOW,
+};
+use crate::{Depth, Gas, Time};
+
+/// deco schedule for real-time ascent, initial schedule stops marked completed once their scheduled
+/// time was spent at the stop (at or below stop depth, within stop window) and ascended past,
+/// completed stops not reappearing in recalculated remaining runtime unless still required
+/// by current ceiling
+#[derive(Clone, Debug)]
+pub struct StickyDecoPlan<T: DecoModel> {
+    model: T,
+    gas_mixes: Vec<Gas>,
+    initial_runtime: DecoRuntime,
+    // initial schedule stops with time spent at the stop
+    served_stops: Vec<(DecoStage, Time)>,
+    completed_stops: Vec<DecoStage>,
+}
+
+impl<T: DecoModel> StickyDecoPlan<T> {
+    pub fn new(model: T, gas_mixes: Vec<Gas>) -> Result<Self, DecoCalculationError> {
+        let initial_runtime = model.deco(gas_mixes.clone())?;
+        let served_stops = initial_runtime
+            .deco_stages
+            .iter()
+            .filter(|stage| stage.stage_type == DecoStageType::DecoStop)
+            .map(|stop| (*stop, Time::zero()))
+            .collect();
+        Ok(Self {
+            model,
+            gas_mixes,
+            initial_runtime,
+            served_stops,
+            completed_stops: vec![],
+        })
+    }
+
+    pub fn model(&self) -> &T {
+        &self.model
+    }
+
+    /// schedule calculated on plan creation
+    pub fn initial_runtime(&self) -> &DecoRuntime {
+        &self.initial_runtime
+    }
+
+    /// initial schedule stops served and ascended past, deepest first
+    pub fn completed_stops(&self) -> &[DecoStage] {
+        &self.completed_stops
+    }
+
+    pub fn record(&mut self, depth: Depth, time: Time, gas: &Gas) {
+        self.model.record(depth, time, gas);
+        self.register_served_time(depth, depth, time);
+        self.mark_completed_stops();
+    }
+
+    pub fn record_travel_with_rate(
+        &mut self,
+        target_depth: Depth,
+        rate: AscentRatePerMinute,
+        gas: &Gas,
+    ) {
+        let start_depth = self.model.dive_state().depth;
+        let start_time = self.model.dive_state().time;
+        self.model.record_travel_with_rate(target_depth, rate, gas);
+        let travel_time = self.model.dive_state().time.saturating_sub(start_time);
+        self.register_served_time(start_depth, target_depth, travel_time);
+        self.mark_completed_stops();
+    }
+
+    /// remaining deco runtime recalculated for current model state, completed stops excluded
+    /// (stops deeper than current ceiling always kept)
+    pub fn runtime(&self) -> Result<DecoRuntime, DecoCalculationError> {
+        let mut runtime = self.model.deco(self.gas_mixes.clone())?;
+        let ceiling = self.model.ceiling();
+        let mut remaining_stages: Vec<DecoStage> = vec![];
+        let mut stop_dropped = false;
+        for stage in runtime.deco_stages.into_iter() {
+            if stage.stage_type == DecoStageType::DecoStop
+                && stage.start_depth.approx_le(ceiling)
+                && self.is_completed_stop_depth(stage.start_depth)
+            {
+                stop_dropped = true;
+                continue;
+            }
+            // ascent leading to dropped stop merged with following ascent
+            match remaining_stages.last_mut() {
+                Some(last_stage)
+                    if stop_dropped
+                        && last_stage.stage_type == DecoStageType::Ascent
+                        && stage.stage_type == DecoStageType::Ascent
+                        && last_stage.gas == stage.gas =>
+                {
+                    last_stage.end_depth = stage.end_depth;
+                    last_stage.duration += stage.duration;
+                }
+                _ => remaining_stages.push(stage),
+            }
+            stop_dropped = false;
+        }
+        // no-op ascents left after merging (e.g. descent to dropped stop and back)
+        remaining_stages.retain(|stage| {
+            stage.stage_type != DecoStageType::Ascent
+                || !stage.start_depth.approx_eq(stage.end_depth)
+        });
+        runtime.tts = remaining_stages.iter().map(|stage| stage.duration).sum();
+        runtime.deco_stages = remaining_stages;
+
+        Ok(runtime)
+    }
+
+    // time spent within each initial schedule stop window (stop depth up to next deeper increment)
+    // during a linear depth change
+    fn register_served_time(&mut self, start_depth: Depth, end_depth: Depth, time: Time) {
+        let shallow = start_depth.min(end_depth).as_meters();
+        let deep = start_depth.max(end_depth).as_meters();
+        for (stop, served_time) in self.served_stops.iter_mut() {
+            let window_top = stop.start_depth.as_meters();
+            let window_bottom = window_top + DEFAULT_CEILING_WINDOW;
+            let served_fraction = match deep - shallow {
+                range if range <= 0. => {
+                    match stop.start_depth.approx_le(start_depth) && shallow < window_bottom {
+                        true => 1.,
+                        false => 0.,
+                    }
+                }
+                range => {
+                    let overlap = deep.min(window_bottom) - shallow.max(window_top);
+                    (overlap / range).clamp(0., 1.)
+                }
+            };
+            *served_time += Time::from_seconds(time.as_seconds() * served_fraction);
+        }
+    }
+
+    // initial schedule stops served and deeper than current depth marked completed
+    fn mark_completed_stops(&mut self) {
+        let depth = self.model.dive_state().depth;
+        let passed_stops: Vec<DecoStage> = self
+            .served_stops
+            .iter()
+            .filter(|(stop, served_time)| {
+                *served_time >= stop.duration
+                    && !stop.start_depth.approx_le(depth)
+                    && !self.is_completed_stop_depth(stop.start_depth)
+            })
+            .map(|(stop, _)| *stop)
+            .collect();
+        self.completed_stops.extend(passed_stops);
+    }
+
+    fn is_completed_stop_depth(&self, depth: Depth) -> bool {
+        self.completed_stops
+            .iter()
+            .any(|stop| stop.start_depth.approx_eq(depth))
+    }
+}
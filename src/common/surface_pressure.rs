@@ -0,0 +1,79 @@
+use super::{MbarPressure, Pressure};
+
+const MBAR_PER_BAR: f64 = 1000.;
+const MBAR_PER_ATM: f64 = 1013.25;
+
+/// atmospheric pressure at the surface
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfacePressure {
+    mbar: f64,
+}
+
+impl Default for SurfacePressure {
+    fn default() -> Self {
+        Self { mbar: 1013. }
+    }
+}
+
+impl From<MbarPressure> for SurfacePressure {
+    fn from(mbar: MbarPressure) -> Self {
+        Self::from_mbar(mbar)
+    }
+}
+
+impl SurfacePressure {
+    pub fn from_mbar(val: MbarPressure) -> Self {
+        Self { mbar: val as f64 }
+    }
+    pub fn from_bar(val: Pressure) -> Self {
+        Self {
+            mbar: val * MBAR_PER_BAR,
+        }
+    }
+    pub fn from_atm(val: f64) -> Self {
+        Self {
+            mbar: val * MBAR_PER_ATM,
+        }
+    }
+    /// pressure in bar
+    pub fn as_bar(&self) -> Pressure {
+        self.mbar / MBAR_PER_BAR
+    }
+    /// pressure in whole millibars (rounded, saturated to mbar pressure range)
+    pub fn as_mbar(&self) -> MbarPressure {
+        self.mbar.round() as MbarPressure
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_atm() {
+        let surface_pressure = SurfacePressure::from_atm(1.);
+        assert_eq!(surface_pressure.as_mbar(), 1013);
+        assert_eq!(surface_pressure.as_bar(), 1.01325);
+    }
+
+    #[test]
+    fn test_bar_round_trip() {
+        for bar in [0.7, 1., 1.013, 1.05] {
+            assert_eq!(SurfacePressure::from_bar(bar).as_bar(), bar);
+        }
+        let surface_pressure = SurfacePressure::from_mbar(1013);
+        assert_eq!(
+            SurfacePressure::from_bar(surface_pressure.as_bar()).as_mbar(),
+            1013
+        );
+    }
+
+    #[test]
+    fn test_from_mbar() {
+        let surface_pressure: SurfacePressure = 1032.into();
+        assert_eq!(surface_pressure, SurfacePressure::from_mbar(1032));
+        assert_eq!(surface_pressure.as_bar(), 1.032);
+        assert_eq!(SurfacePressure::default().as_mbar(), 1013);
+    }
+}
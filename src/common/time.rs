@@ -1,9 +1,11 @@
 use std::{
     cmp::Ordering,
+    iter::Sum,
     ops::{Add, AddAssign, Div, Mul, Sub},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Time {
     s: f64,
 }
@@ -46,6 +48,16 @@ impl Div<Self> for Time {
         Self { s: self.s / rhs.s }
     }
 }
+impl Sum for Time {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), |acc, time| acc + time)
+    }
+}
+impl<'a> Sum<&'a Time> for Time {
+    fn sum<I: Iterator<Item = &'a Time>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
 impl PartialOrd for Time {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.s.partial_cmp(&other.s)
@@ -68,6 +80,18 @@ impl Time {
     pub fn as_minutes(&self) -> f64 {
         self.s / 60.
     }
+    /// sum of time values (e.g. stage durations)
+    pub fn total(times: &[Time]) -> Self {
+        times.iter().sum()
+    }
+    /// subtraction capped at zero
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        let s = self.s - rhs.s;
+        match s > 0. {
+            true => Self { s },
+            false => Self::zero(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -97,4 +121,36 @@ mod tests {
         let time = Time::from_seconds(30.0);
         assert_eq!(time.as_minutes(), 0.5);
     }
+
+    #[test]
+    fn test_saturating_sub() {
+        let time = Time::from_seconds(120.);
+        assert_eq!(
+            time.saturating_sub(Time::from_seconds(20.)),
+            Time::from_seconds(100.)
+        );
+        // float jitter on zero-length segment
+        let jittery_time = Time::from_seconds(0.1 + 0.2);
+        assert_eq!(
+            Time::from_seconds(0.3).saturating_sub(jittery_time),
+            Time::zero()
+        );
+        assert!((Time::from_seconds(0.3) - jittery_time) < Time::zero());
+    }
+
+    #[test]
+    fn test_sum() {
+        let times = vec![
+            Time::from_seconds(30.),
+            Time::from_minutes(1.),
+            Time::from_seconds(15.),
+        ];
+        assert_eq!(times.iter().sum::<Time>(), Time::from_seconds(105.));
+        assert_eq!(times.into_iter().sum::<Time>(), Time::from_seconds(105.));
+        assert_eq!(Time::total(&[]), Time::zero());
+        assert_eq!(
+            Time::total(&[Time::from_minutes(1.), Time::from_minutes(2.)]),
+            Time::from_minutes(3.)
+        );
+    }
 }
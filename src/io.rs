@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::{Depth, Gas, ProfileSegment, Time};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    // 1-based line number in parsed input
+    pub line: usize,
+    pub reason: String,
+}
+
+impl ParseError {
+    fn new(line: usize, reason: impl Into<String>) -> Self {
+        Self {
+            line,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.reason)
+    }
+}
+
+/// parse dive profile samples in "time_s,depth_m,gas" CSV format (e.g. Subsurface export) into
+/// profile segments, each a linear travel from the previous sample (or surface at 0s).
+/// time is elapsed dive time in seconds, gas as `air`, `EANxx` or `O2/He` percentages
+/// (e.g. `21/35`), optional header row and empty lines skipped
+pub fn parse_profile_csv(csv: &str) -> Result<Vec<ProfileSegment>, ParseError> {
+    let mut segments: Vec<ProfileSegment> = vec![];
+    let mut last_timestamp = Time::zero();
+    for (i, row) in csv.lines().enumerate() {
+        let line = i + 1;
+        let row = row.trim();
+        if row.is_empty() || (segments.is_empty() && row.starts_with("time")) {
+            continue;
+        }
+
+        let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+        let [time_field, depth_field, gas_field] = fields[..] else {
+            return Err(ParseError::new(
+                line,
+                format!(
+                    "Expected 3 fields (time_s,depth_m,gas), found {}",
+                    fields.len()
+                ),
+            ));
+        };
+
+        let timestamp = Time::from_seconds(parse_number(line, "time", time_field)?);
+        if timestamp < last_timestamp {
+            return Err(ParseError::new(
+                line,
+                format!(
+                    "Time [{}s] earlier than previous sample [{}s]",
+                    timestamp.as_seconds(),
+                    last_timestamp.as_seconds()
+                ),
+            ));
+        }
+        let depth = Depth::from_meters(parse_number(line, "depth", depth_field)?);
+        let gas = parse_gas(line, gas_field)?;
+
+        segments.push(ProfileSegment {
+            depth,
+            time: timestamp - last_timestamp,
+            gas,
+        });
+        last_timestamp = timestamp;
+    }
+
+    Ok(segments)
+}
+
+// non-negative finite number
+fn parse_number(line: usize, field: &str, value: &str) -> Result<f64, ParseError> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number >= 0. => Ok(number),
+        _ => Err(ParseError::new(
+            line,
+            format!("Invalid {} value [{}]", field, value),
+        )),
+    }
+}
+
+fn parse_gas(line: usize, value: &str) -> Result<Gas, ParseError> {
+    let invalid_gas = || ParseError::new(line, format!("Invalid gas [{}]", value));
+    let percentage = |value: &str| {
+        value
+            .parse::<f64>()
+            .map(|percentage| percentage / 100.)
+            .map_err(|_| invalid_gas())
+    };
+
+    let gas_id = value.to_lowercase();
+    if gas_id == "air" {
+        return Ok(Gas::air());
+    }
+    let (o2, he) = match (gas_id.strip_prefix("ean"), gas_id.split_once('/')) {
+        (Some(o2), _) => (percentage(o2)?, 0.),
+        (None, Some((o2, he))) => (percentage(o2)?, percentage(he)?),
+        _ => return Err(invalid_gas()),
+    };
+
+    Gas::try_new(o2, he)
+        .map_err(|e| ParseError::new(line, format!("Invalid gas [{}]: {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gas() {
+        assert_eq!(parse_gas(1, "air"), Ok(Gas::air()));
+        assert_eq!(parse_gas(1, "EAN32"), Ok(Gas::new(0.32, 0.)));
+        assert_eq!(parse_gas(1, "21/35"), Ok(Gas::new(0.21, 0.35)));
+        assert_eq!(
+            parse_gas(3, "nitrox"),
+            Err(ParseError::new(3, "Invalid gas [nitrox]"))
+        );
+        assert!(parse_gas(1, "80/40").is_err());
+    }
+}
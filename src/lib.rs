@@ -1,10 +1,19 @@
 mod buehlmann;
 mod common;
+#[cfg(feature = "io")]
+pub mod io;
 
-pub use buehlmann::{BuehlmannConfig, BuehlmannModel, Compartment, Supersaturation};
+pub use buehlmann::{
+    BuehlmannConfig, BuehlmannModel, Checkpoint, Compartment, GfLine, InertLoading,
+    Supersaturation, ZHLParam, ZHLParams, ZHL_16C_N2_16A_HE_VALUES,
+};
 
 pub use common::{
-    CeilingType, Deco, DecoCalculationError, DecoModel, DecoRuntime, DecoStage, DecoStageType,
-    Depth, DepthType, DiveState, Gas, GradientFactors, NDLType, Pressure, RecordData, Sim, Time,
-    Unit, Units,
+    AscentRate, CNSCoeffRow, CeilingType, ConfigValidationErr, Conservatism, Deco,
+    DecoCalculationError, DecoDiff, DecoIter, DecoModel, DecoRuntime, DecoSample, DecoSession,
+    DecoStage, DecoStageType, DecoStopDiff, Depth, DepthType, DiveState, Gas, GasError,
+    GasPlanEntry, GasSelectionOutcome, GasSelectionReason, GfLowReference, GradientFactors,
+    NDLType, PartialPressures, Pressure, ProfileSegment, RecordData, RecordError, RunListEntry,
+    SafetyStop, Sim, StickyDecoPlan, StopRounding, SurfacePressure, Time, Unit, Units,
+    CNS_COEFFICIENTS,
 };
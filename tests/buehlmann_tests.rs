@@ -1,5 +1,7 @@
 use dive_deco::{
-    BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel, Depth, Gas, Supersaturation, Time,
+    BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel, DecoStageType, Depth, DiveState, Gas,
+    GfLowReference, ProfileSegment, RecordError, Sim, Supersaturation, Time,
+    ZHL_16C_N2_16A_HE_VALUES,
 };
 pub mod fixtures;
 
@@ -15,6 +17,88 @@ fn test_should_panic_on_invalid_depth() {
     );
 }
 
+#[test]
+fn test_try_record_invalid_depth() {
+    let mut model = fixtures::model_default();
+    let res = model.try_record(
+        Depth::from_meters(-10.),
+        Time::from_seconds(1.),
+        &fixtures::gas_air(),
+    );
+    assert_eq!(
+        res,
+        Err(RecordError::InvalidDepth(Depth::from_meters(-10.)))
+    );
+    assert_eq!(model.dive_state().depth, Depth::zero());
+    assert_eq!(model.dive_state().time, Time::zero());
+
+    let res = model.try_record(
+        Depth::from_meters(10.),
+        Time::from_seconds(1.),
+        &fixtures::gas_air(),
+    );
+    assert_eq!(res, Ok(()));
+    assert_eq!(model.dive_state().depth, Depth::from_meters(10.));
+}
+
+#[test]
+fn test_try_record_depth_exceeding_model_limit() {
+    let heliox = Gas::new(0.05, 0.95);
+    let mut model =
+        BuehlmannModel::new(BuehlmannConfig::default().with_max_depth(Depth::from_meters(350.)));
+    let res = model.try_record(Depth::from_meters(400.), Time::from_seconds(1.), &heliox);
+    assert_eq!(
+        res,
+        Err(RecordError::DepthExceedsModelLimit(Depth::from_meters(
+            400.
+        )))
+    );
+    assert_eq!(model.dive_state().depth, Depth::zero());
+
+    let res = model.try_record(Depth::from_meters(300.), Time::from_seconds(1.), &heliox);
+    assert_eq!(res, Ok(()));
+    assert_eq!(model.dive_state().depth, Depth::from_meters(300.));
+}
+
+#[test]
+#[should_panic]
+fn test_should_panic_on_travel_beyond_model_limit() {
+    let mut model = fixtures::model_default();
+    model.record_travel_with_rate(Depth::from_meters(400.), 20., &Gas::new(0.05, 0.95));
+}
+
+#[test]
+fn test_record_at() {
+    let air = Gas::air();
+    let mut timestamp_model = fixtures::model_default();
+    let mut delta_model = fixtures::model_default();
+    for (timestamp, depth) in [(0., 10.), (60., 20.), (120., 30.)] {
+        timestamp_model
+            .record_at(
+                Time::from_seconds(timestamp),
+                Depth::from_meters(depth),
+                &air,
+            )
+            .unwrap();
+    }
+    delta_model.record(Depth::from_meters(10.), Time::zero(), &air);
+    delta_model.record(Depth::from_meters(20.), Time::from_seconds(60.), &air);
+    delta_model.record(Depth::from_meters(30.), Time::from_seconds(60.), &air);
+
+    assert_eq!(timestamp_model.tissues(), delta_model.tissues());
+    assert_eq!(
+        timestamp_model.dive_state().time,
+        delta_model.dive_state().time
+    );
+
+    let res = timestamp_model.record_at(Time::from_seconds(90.), Depth::from_meters(30.), &air);
+    assert_eq!(
+        res,
+        Err(RecordError::TimestampInPast(Time::from_seconds(90.)))
+    );
+    assert_eq!(timestamp_model.dive_state().time, Time::from_seconds(120.));
+}
+
 #[test]
 fn test_ceiling() {
     let mut model = fixtures::model_default();
@@ -268,3 +352,1116 @@ fn test_cns_otu() {
     model.record_travel_with_rate(Depth::from_meters(0.), 10., &Gas::air());
     assert_close_to_abs!(model.otu(), 13., 1.);
 }
+
+#[test]
+fn test_checkpoint_restore() {
+    let mut model = fixtures::model_gf((30, 70));
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let ean_32 = Gas::new(0.32, 0.);
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let checkpoint = model.checkpoint();
+    let checkpoint_tissues = model.tissues();
+    let checkpoint_ceiling = model.ceiling();
+    let checkpoint_cns = model.cns();
+
+    // option 1: switch to EAN50
+    model.record_travel_with_rate(Depth::from_meters(21.), 10., &air);
+    model.record(Depth::from_meters(21.), Time::from_minutes(5.), &ean_50);
+    let ean_50_ceiling = model.ceiling();
+    assert!(model.cns() > checkpoint_cns);
+
+    model.restore(checkpoint.clone());
+    assert_eq!(model.tissues(), checkpoint_tissues);
+    assert_eq!(model.ceiling(), checkpoint_ceiling);
+    assert_eq!(model.cns(), checkpoint_cns);
+    assert_eq!(model.dive_state().depth, Depth::from_meters(40.));
+
+    // option 2: switch to EAN32
+    model.record_travel_with_rate(Depth::from_meters(21.), 10., &air);
+    model.record(Depth::from_meters(21.), Time::from_minutes(5.), &ean_32);
+    let ean_32_ceiling = model.ceiling();
+    assert!(ean_50_ceiling < ean_32_ceiling);
+
+    model.restore(checkpoint);
+    assert_eq!(model.tissues(), checkpoint_tissues);
+    assert_eq!(model.ceiling(), checkpoint_ceiling);
+    assert!(!model.is_sim());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_checkpoint_serde() {
+    let mut model = fixtures::model_gf((30, 70));
+    let air = Gas::air();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let checkpoint = model.checkpoint();
+    let json = serde_json::to_string(&checkpoint).unwrap();
+
+    // persisted checkpoint restored on a fresh model
+    let mut restored_model = fixtures::model_gf((30, 70));
+    restored_model.restore(serde_json::from_str(&json).unwrap());
+    for (restored_comp, comp) in restored_model.tissues().iter().zip(model.tissues().iter()) {
+        assert_close_to_abs!(restored_comp.total_ip, comp.total_ip, 1e-9);
+    }
+    assert_close_to_abs!(
+        restored_model.ceiling().as_meters(),
+        model.ceiling().as_meters(),
+        1e-9
+    );
+    assert_eq!(restored_model.dive_state().depth, Depth::from_meters(40.));
+}
+
+#[test]
+fn test_water_vapor_pressure() {
+    let air = Gas::air();
+    let mut default_model = fixtures::model_default();
+    let mut schreiner_model =
+        BuehlmannModel::new(BuehlmannConfig::new().with_water_vapor_pressure(0.0493));
+    default_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    schreiner_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    assert!(schreiner_model.ceiling() > default_model.ceiling());
+}
+
+#[test]
+fn test_respiratory_quotient() {
+    let air = Gas::air();
+    let depth = Depth::from_meters(40.);
+    let time = Time::from_minutes(20.);
+    let mut default_model = fixtures::model_default();
+    let mut rq_model = BuehlmannModel::new(BuehlmannConfig::new().with_respiratory_quotient(0.8));
+    let mut neutral_rq_model =
+        BuehlmannModel::new(BuehlmannConfig::new().with_respiratory_quotient(1.));
+    default_model.record(depth, time, &air);
+    rq_model.record(depth, time, &air);
+    neutral_rq_model.record(depth, time, &air);
+
+    assert!(rq_model.ceiling() > default_model.ceiling());
+    assert_eq!(neutral_rq_model.ceiling(), default_model.ceiling());
+}
+
+#[test]
+fn test_safe_to_surface() {
+    let air = Gas::air();
+    let mut model =
+        BuehlmannModel::new(BuehlmannConfig::new().with_ceiling_type(CeilingType::Adaptive));
+    model.record(Depth::from_meters(30.), Time::from_minutes(10.), &air);
+    assert!(model.safe_to_surface());
+
+    // longer time at 30m, adaptive ceiling (simulated ascent with off-gassing) still clear
+    // while instant surfacing exceeds GF high
+    model.record(Depth::from_meters(30.), Time::from_minutes(7.), &air);
+    assert_eq!(model.ceiling(), Depth::zero());
+    assert!(!model.safe_to_surface());
+}
+
+#[test]
+fn test_time_to_next_ceiling_change() {
+    let air = Gas::air();
+    let stop_window = Depth::from_meters(3.);
+    let mut model = fixtures::model_gf((30, 70));
+    assert_eq!(model.time_to_next_ceiling_change(), None);
+
+    // ceiling not getting shallower at depth
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    assert_eq!(model.time_to_next_ceiling_change(), None);
+
+    model.record_travel_with_rate(Depth::from_meters(9.), 10., &air);
+    let initial_stop = model.ceiling().ceil_to(stop_window);
+
+    let time_to_change = model.time_to_next_ceiling_change().unwrap();
+    assert!(time_to_change > Time::zero());
+    model.record(Depth::from_meters(9.), time_to_change, &air);
+    assert_eq!(
+        model.ceiling().ceil_to(stop_window),
+        initial_stop - stop_window
+    );
+}
+
+#[test]
+fn test_time_to_next_ceiling_change_configured_ceiling() {
+    let air = Gas::air();
+    let stop_window = Depth::from_meters(3.);
+    let configs = [
+        BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_ceiling_type(CeilingType::GfSurface),
+        BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_ceiling_hysteresis(Depth::from_meters(1.)),
+    ];
+    for config in configs {
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        model.record_travel_with_rate(Depth::from_meters(9.), 10., &air);
+        let initial_stop = model.ceiling().ceil_to(stop_window);
+
+        // reported ceiling moved one stop increment
+        let time_to_change = model.time_to_next_ceiling_change().unwrap();
+        model.record(Depth::from_meters(9.), time_to_change, &air);
+        assert_eq!(
+            model.ceiling().ceil_to(stop_window),
+            initial_stop - stop_window
+        );
+    }
+}
+
+#[test]
+fn test_custom_compartments() {
+    let air = Gas::air();
+    let mut default_model = BuehlmannModel::default();
+    let mut zhl_16_model = BuehlmannModel::new_with_compartments(
+        BuehlmannConfig::default(),
+        &ZHL_16C_N2_16A_HE_VALUES,
+    );
+    default_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    zhl_16_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    assert_eq!(zhl_16_model.ceiling(), default_model.ceiling());
+    assert_eq!(zhl_16_model.tissues(), default_model.tissues());
+
+    // reduced compartments set, fast compartments only
+    let mut reduced_model = BuehlmannModel::new_with_compartments(
+        BuehlmannConfig::default(),
+        &ZHL_16C_N2_16A_HE_VALUES[..4],
+    );
+    reduced_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    assert_eq!(reduced_model.tissues().len(), 4);
+    assert!(reduced_model.ceiling() <= default_model.ceiling());
+}
+
+#[test]
+fn test_simulate_surface_gf() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(15.), &air);
+    let initial_tissues = model.tissues();
+
+    let fast_ascent = [ProfileSegment {
+        depth: Depth::zero(),
+        time: Time::from_minutes(2.),
+        gas: air,
+    }];
+    let slow_ascent = [
+        ProfileSegment {
+            depth: Depth::from_meters(6.),
+            time: Time::from_minutes(4.),
+            gas: air,
+        },
+        ProfileSegment {
+            depth: Depth::from_meters(6.),
+            time: Time::from_minutes(5.),
+            gas: air,
+        },
+    ];
+    let fast_ascent_gf = model.simulate_surface_gf(&fast_ascent);
+    let slow_ascent_gf = model.simulate_surface_gf(&slow_ascent);
+    assert!(slow_ascent_gf < fast_ascent_gf);
+    assert_eq!(model.tissues(), initial_tissues);
+    assert_eq!(model.dive_state().depth, Depth::from_meters(40.));
+}
+
+#[test]
+fn test_desaturation_time() {
+    let air = Gas::air();
+
+    let mut shallow_dive_model = fixtures::model_default();
+    shallow_dive_model.record(Depth::from_meters(10.), Time::from_minutes(20.), &air);
+    shallow_dive_model.record(Depth::zero(), Time::zero(), &air);
+
+    let mut deco_dive_model = fixtures::model_default();
+    deco_dive_model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    deco_dive_model.record(Depth::zero(), Time::zero(), &air);
+
+    assert_eq!(fixtures::model_default().desaturation_time(), Time::zero());
+    assert!(deco_dive_model.desaturation_time() > shallow_dive_model.desaturation_time());
+    assert!(deco_dive_model.no_fly_time() > Time::zero());
+    assert!(deco_dive_model.no_fly_time() >= shallow_dive_model.no_fly_time());
+    assert!(deco_dive_model.no_fly_time() < deco_dive_model.desaturation_time());
+}
+
+#[test]
+fn test_no_fly_time_compartment_gf() {
+    let air = Gas::air();
+    let no_fly_time = |config: BuehlmannConfig| {
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        model.record(Depth::zero(), Time::zero(), &air);
+        model.no_fly_time()
+    };
+
+    // compartment-specific GF high applied instead of global one
+    let config = BuehlmannConfig::default().with_gradient_factors(30, 70);
+    let conservative_config = BuehlmannConfig::default().with_gradient_factors(30, 30);
+    let per_compartment_config = config.with_per_compartment_gf(Some([(30, 30); 16]));
+    assert!(no_fly_time(conservative_config) > no_fly_time(config));
+    assert_eq!(
+        no_fly_time(per_compartment_config),
+        no_fly_time(conservative_config)
+    );
+
+    // surface saturation never within GF high at cabin pressure, capped at 72h
+    let high_surface_pressure_config = BuehlmannConfig::default()
+        .with_surface_pressure(1100)
+        .with_gradient_factors(10, 10);
+    assert_eq!(
+        no_fly_time(high_surface_pressure_config),
+        Time::from_minutes(72. * 60.)
+    );
+}
+
+#[test]
+fn test_supersaturation_at_pressure() {
+    let mut model = fixtures::model_default();
+    let air = Gas::air();
+    model.record(Depth::from_meters(30.), Time::from_minutes(20.), &air);
+    model.record_travel_with_rate(Depth::zero(), 10., &air);
+
+    let sea_level_supersaturation = model.supersaturation_at_pressure(1013);
+    let altitude_supersaturation = model.supersaturation_at_pressure(700);
+    assert_eq!(sea_level_supersaturation, model.supersaturation());
+    assert!(altitude_supersaturation.gf_surf > sea_level_supersaturation.gf_surf);
+    // model config unchanged
+    assert_eq!(model.config().surface_pressure, 1013);
+}
+
+#[test]
+fn test_current_partial_pressures() {
+    let mut model = fixtures::model_default();
+    let air = Gas::air();
+    model.record(Depth::from_meters(30.), Time::from_minutes(10.), &air);
+
+    assert_close_to_abs!(model.current_pp_o2(), 0.21 * 4.013, 1e-9);
+    let inspired_partial_pressures = model.current_inspired_partial_pressures();
+    assert_close_to_abs!(
+        inspired_partial_pressures.o2,
+        0.21 * (4.013 - model.config().water_vapor_pressure),
+        1e-9
+    );
+    assert!(inspired_partial_pressures.o2 < model.current_pp_o2());
+}
+
+#[test]
+fn test_ceiling_hysteresis() {
+    let air = Gas::air();
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_round_ceiling(true);
+    let mut model = BuehlmannModel::new(config);
+    let mut hysteretic_model =
+        BuehlmannModel::new(config.with_ceiling_hysteresis(Depth::from_meters(1.)));
+    for m in [&mut model, &mut hysteretic_model] {
+        m.record(Depth::from_meters(40.), Time::from_minutes(25.), &air);
+        m.record_travel_with_rate(Depth::from_meters(12.), 9., &air);
+    }
+
+    // stop with depth fluctuations
+    let mut model_ceiling_increased = false;
+    let mut last_ceiling = model.ceiling();
+    let mut last_hysteretic_ceiling = hysteretic_model.ceiling();
+    for i in 0..40 {
+        let depth = Depth::from_meters(if i % 2 == 0 { 11. } else { 13. });
+        model.record(depth, Time::from_seconds(30.), &air);
+        hysteretic_model.record(depth, Time::from_seconds(30.), &air);
+
+        let ceiling = model.ceiling();
+        let hysteretic_ceiling = hysteretic_model.ceiling();
+        if ceiling > last_ceiling {
+            model_ceiling_increased = true;
+        }
+        assert!(hysteretic_ceiling <= last_hysteretic_ceiling);
+        assert!(hysteretic_ceiling >= ceiling);
+        assert!(hysteretic_ceiling - ceiling <= Depth::from_meters(1.));
+        last_ceiling = ceiling;
+        last_hysteretic_ceiling = hysteretic_ceiling;
+    }
+    assert!(model_ceiling_increased);
+}
+
+#[test]
+fn test_ceiling_hysteresis_not_affecting_tissues() {
+    let air = Gas::air();
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_round_ceiling(true);
+    let mut model = BuehlmannModel::new(config);
+    let mut hysteretic_model =
+        BuehlmannModel::new(config.with_ceiling_hysteresis(Depth::from_meters(1.)));
+    for m in [&mut model, &mut hysteretic_model] {
+        m.record(Depth::from_meters(40.), Time::from_minutes(25.), &air);
+        m.record_travel_with_rate(Depth::from_meters(6.), 9., &air);
+    }
+
+    // oscillating around shallow stops until cleared and past, hysteretic ceiling held up
+    let mut hysteresis_held = false;
+    for i in 0..120 {
+        let depth = Depth::from_meters(if i % 2 == 0 { 2.5 } else { 4. });
+        model.record(depth, Time::from_seconds(30.), &air);
+        hysteretic_model.record(depth, Time::from_seconds(30.), &air);
+        if hysteretic_model.ceiling() > model.ceiling() {
+            hysteresis_held = true;
+        }
+
+        let tissue_values = |m: &BuehlmannModel| {
+            m.tissues()
+                .iter()
+                .map(|t| (t.total_ip, t.min_tolerable_amb_pressure, t.m_value_calc))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(tissue_values(&hysteretic_model), tissue_values(&model));
+        assert_eq!(hysteretic_model.gf_low_depth(), model.gf_low_depth());
+    }
+    assert!(hysteresis_held);
+    assert_eq!(model.ceiling(), Depth::zero());
+}
+
+#[test]
+fn test_icd_risk_on_switch() {
+    let tmx = Gas::new(0.18, 0.45);
+    let ean_50 = Gas::new(0.50, 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(60.), Time::from_minutes(20.), &tmx);
+    model.record_travel_with_rate(Depth::from_meters(21.), 9., &tmx);
+
+    // initial inert gas pressure change rate for fastest compartments, inspired gas at 21m (3.113 bar)
+    // minus alveolar water vapor pressure
+    let inspired_pressure = 3.113 - model.config().water_vapor_pressure;
+    let n2_inspired = ean_50.fractions().2 * inspired_pressure;
+    let expected_risk = model.tissues().iter().take(5).any(|comp| {
+        let (n2_half_time, he_half_time) = comp.half_times();
+        let n2_rate = (n2_inspired - comp.n2_ip) * 2_f64.ln() / n2_half_time;
+        let he_rate = (0. - comp.he_ip) * 2_f64.ln() / he_half_time;
+        n2_rate + he_rate > 0.
+    });
+
+    assert_eq!(model.icd_risk_on_switch(&ean_50), expected_risk);
+    assert!(!expected_risk);
+    // staying on current gas while off-gassing
+    assert!(!model.icd_risk_on_switch(&tmx));
+
+    // switch to high N2 mix while fast tissues still N2 undersaturated
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(30.), Time::from_minutes(1.), &tmx);
+    assert!(model.icd_risk_on_switch(&Gas::air()));
+}
+
+#[test]
+fn test_switch_gas() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    model.record_travel_with_rate(Depth::from_meters(22.), 10., &air);
+    let DiveState { time, .. } = model.dive_state();
+
+    let err = model.switch_gas(&oxygen).unwrap_err();
+    assert!(matches!(err, RecordError::PpO2LimitExceeded(pp_o2) if pp_o2 > 1.6));
+    assert_eq!(model.dive_state().gas, air);
+
+    assert_eq!(model.switch_gas(&ean_50), Ok(()));
+    let state = model.dive_state();
+    assert_eq!(state.gas, ean_50);
+    assert_eq!(state.depth, Depth::from_meters(22.));
+    assert_eq!(state.time, time);
+
+    // bottom gas (not more oxygen-rich than current gas) within bottom ppO2 limit
+    let ean_32 = Gas::new(0.32, 0.);
+    let mut descent_model = fixtures::model_default();
+    descent_model.record(Depth::from_meters(35.), Time::zero(), &ean_50);
+    let err = descent_model.switch_gas(&ean_32).unwrap_err();
+    assert!(matches!(err, RecordError::PpO2LimitExceeded(pp_o2) if pp_o2 > 1.4 && pp_o2 < 1.6));
+    let mut descent_model =
+        BuehlmannModel::new(BuehlmannConfig::default().with_bottom_pp_o2_limit(1.6));
+    descent_model.record(Depth::from_meters(35.), Time::zero(), &ean_50);
+    assert_eq!(descent_model.switch_gas(&ean_32), Ok(()));
+
+    // hypoxic mix at shallow depth
+    let tmx_10_70 = Gas::new(0.1, 0.7);
+    model.record_travel_with_rate(Depth::from_meters(3.), 10., &ean_50);
+    let err = model.switch_gas(&tmx_10_70).unwrap_err();
+    assert!(matches!(err, RecordError::PpO2BelowMin(pp_o2) if pp_o2 < 0.18));
+    assert_eq!(model.dive_state().gas, ean_50);
+}
+
+#[test]
+fn test_travel_schreiner_equation() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    let water_vapor_pressure = model.config().water_vapor_pressure;
+    let (_, _, n2_fraction, _) = air.fractions();
+    // slow descent to 60m in 60 min (0.1 bar/min)
+    model.record_travel(Depth::from_meters(60.), Time::from_minutes(60.), &air);
+
+    let t = 60.;
+    let rate = 0.1 * n2_fraction;
+    let initial_n2_ip = (1.013 - water_vapor_pressure) * n2_fraction;
+    let max_interval_lag = rate * (0.5 / 60.) + 1e-9;
+    for comp in model.tissues() {
+        let (n2_half_time, _) = comp.half_times();
+        let k = 2_f64.ln() / n2_half_time;
+        // Schreiner equation, tissue initially in equilibrium with inspired gas
+        let schreiner_n2_ip = initial_n2_ip + rate * (t - 1. / k) + (rate / k) * (-k * t).exp();
+        // bounded by half interval lag of 1s Haldane steps, no accumulated depth error
+        assert_close_to_abs!(comp.n2_ip, schreiner_n2_ip, max_interval_lag);
+    }
+    assert_eq!(model.dive_state().depth, Depth::from_meters(60.));
+}
+
+#[test]
+fn test_zero_time_record_keeps_saturation() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let initial_tissues = model.tissues();
+
+    for depth in [0., 5., 21., 40., 60.] {
+        model.record(Depth::from_meters(depth), Time::zero(), &ean_50);
+        assert_eq!(model.dive_state().depth, Depth::from_meters(depth));
+        for (comp, initial_comp) in model.tissues().iter().zip(initial_tissues.iter()) {
+            assert_eq!(comp.total_ip, initial_comp.total_ip);
+            assert_eq!(comp.n2_ip, initial_comp.n2_ip);
+            assert_eq!(comp.he_ip, initial_comp.he_ip);
+        }
+    }
+}
+
+#[test]
+fn test_inert_loading() {
+    let heliox = Gas::new(0.21, 0.79);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(10.), &heliox);
+
+    let loading = model.inert_loading();
+    assert_eq!(loading.len(), 16);
+    for (entry, comp) in loading.iter().zip(model.tissues().iter()) {
+        assert_eq!(entry.compartment_no, comp.no);
+        assert_eq!(entry.n2_ip, comp.n2_ip);
+        assert_eq!(entry.he_ip, comp.he_ip);
+        assert_eq!(entry.h2_ip, comp.h2_ip);
+    }
+    // He dominates in fast compartments
+    let fastest = &loading[0];
+    assert!(fastest.he_ip > fastest.n2_ip);
+    assert!(fastest.he_n2_ratio > 1.);
+
+    // He washout on shallow air phase
+    model.record(Depth::from_meters(6.), Time::from_minutes(10.), &Gas::air());
+    for (entry, initial_entry) in model.inert_loading().iter().zip(loading.iter()) {
+        assert!(entry.he_ip < initial_entry.he_ip);
+        assert!(entry.he_n2_ratio < initial_entry.he_n2_ratio);
+    }
+}
+
+#[test]
+fn test_inert_loading_without_n2() {
+    let config = BuehlmannConfig::default();
+    // heliox saturated tissues, no N2
+    let heliox = Gas::new(0.21, 0.79);
+    let model = BuehlmannModel::at_equilibrium(config, Depth::from_meters(40.), &heliox);
+    for entry in model.inert_loading() {
+        assert_eq!(entry.n2_ip, 0.);
+        assert!(entry.he_ip > 0.);
+        assert_eq!(entry.he_n2_ratio, f64::INFINITY);
+    }
+
+    // hydrox saturated tissues, H2 only
+    let hydrox = Gas::new_with_h2(0.02, 0., 0.98);
+    let model = BuehlmannModel::at_equilibrium(config, Depth::from_meters(100.), &hydrox);
+    for (entry, comp) in model.inert_loading().iter().zip(model.tissues().iter()) {
+        assert!(entry.h2_ip > 0.);
+        assert_eq!(entry.h2_ip, comp.h2_ip);
+        assert_eq!(entry.he_n2_ratio, 0.);
+        assert_close_to_abs!(entry.n2_ip + entry.he_ip + entry.h2_ip, comp.total_ip, 1e-9);
+    }
+}
+
+#[test]
+fn test_ceiling_on_gas() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    model.record_travel_with_rate(Depth::from_meters(21.), 9., &air);
+
+    // no instantaneous ceiling change on switch
+    assert_eq!(model.ceiling_on_gas(&ean_50), model.ceiling_on_gas(&air));
+    assert_eq!(model.ceiling_on_gas(&ean_50), model.ceiling());
+    assert_eq!(model.dive_state().gas, air);
+
+    // lower ceiling after off-gassing on deco gas
+    let mut air_model = model.clone();
+    let mut ean_50_model = model.clone();
+    air_model.record(Depth::from_meters(21.), Time::from_minutes(1.), &air);
+    ean_50_model.record(Depth::from_meters(21.), Time::from_minutes(1.), &ean_50);
+    assert!(ean_50_model.ceiling() < air_model.ceiling());
+}
+
+#[test]
+fn test_model_approx_eq() {
+    let air = Gas::air();
+    let mut model1 = fixtures::model_default();
+    let mut model2 = fixtures::model_default();
+    assert_eq!(model1, model2);
+
+    model1.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    // record every second
+    for _ in 0..1200 {
+        model2.record(Depth::from_meters(40.), Time::from_seconds(1.), &air);
+    }
+    assert!(model1.approx_eq(&model2, 1e-6));
+
+    let mut model3 = model2.clone();
+    model3.record(Depth::from_meters(40.), Time::from_seconds(10.), &air);
+    assert!(!model1.approx_eq(&model3, 1e-6));
+    let different_config_model =
+        BuehlmannModel::new(BuehlmannConfig::new().with_gradient_factors(30, 70));
+    assert!(!fixtures::model_default().approx_eq(&different_config_model, 1e-6));
+}
+
+#[test]
+fn test_ndl_matches_linear_scan() {
+    let air = Gas::air();
+    // NDL by minute-by-minute simulation
+    let linear_ndl = |model: &BuehlmannModel| {
+        let mut sim_model = model.clone();
+        for i in 0..99 {
+            sim_model.record(model.dive_state().depth, Time::from_minutes(1.), &air);
+            if sim_model.in_deco() {
+                return Time::from_minutes(i as f64);
+            }
+        }
+        Time::from_minutes(99.)
+    };
+    for gf in [(100, 100), (30, 70)] {
+        for depth in 10..=40 {
+            let mut model = fixtures::model_gf(gf);
+            model.record(Depth::from_meters(depth as f64), Time::zero(), &air);
+            assert_eq!(model.ndl(), linear_ndl(&model), "{}m, GF {:?}", depth, gf);
+        }
+    }
+}
+
+#[test]
+fn test_gf_line() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    assert_eq!(model.gf_line(), None);
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+
+    let gf_line = model.gf_line().unwrap();
+    assert_eq!((gf_line.gf_low, gf_line.gf_high), (30, 70));
+    // GF low depth at ceiling on GF low (rounded to 1m search step)
+    assert!(gf_line.gf_low_depth >= Depth::from_meters(15.));
+    assert!(gf_line.gf_low_depth <= Depth::from_meters(25.));
+    assert!((gf_line.gf_low_depth - model.ceiling()).as_meters().abs() <= 1.);
+    // line endpoints
+    assert_eq!(gf_line.gf_at_depth(Depth::from_meters(40.)), 30.);
+    assert_eq!(gf_line.gf_at_depth(gf_line.gf_low_depth), 30.);
+    assert_eq!(gf_line.gf_at_depth(Depth::zero()), 70.);
+    assert_eq!(model.gf_low_reference_depth(), Some(gf_line.gf_low_depth));
+}
+
+#[test]
+fn test_max_loading_with() {
+    let air = Gas::air();
+    let ean_32 = Gas::new(0.32, 0.);
+    let mut air_model = fixtures::model_gf((30, 70));
+    let mut ean_32_model = fixtures::model_gf((30, 70));
+    for (model, gas) in [(&mut air_model, air), (&mut ean_32_model, ean_32)] {
+        model.record(Depth::from_meters(30.), Time::from_minutes(40.), &gas);
+        model.record_travel_with_rate(Depth::from_meters(10.), 9., &gas);
+    }
+    // buddy on EAN32 with the same profile, air model's compartments more loaded
+    let merged_model = ean_32_model.max_loading_with(&air_model).unwrap();
+    assert!(air_model.ceiling() > ean_32_model.ceiling());
+    assert_eq!(merged_model.ceiling(), air_model.ceiling());
+    assert_eq!(merged_model.dive_state().gas, ean_32);
+    for (comp, air_comp) in merged_model.tissues().iter().zip(air_model.tissues()) {
+        assert_eq!(comp.total_ip, air_comp.total_ip);
+    }
+
+    let other_config_model = fixtures::model_gf((50, 80));
+    assert!(air_model.max_loading_with(&other_config_model).is_err());
+}
+
+#[test]
+fn test_average_depth() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    assert_eq!(model.average_depth(), Depth::zero());
+    model.record(Depth::from_meters(10.), Time::from_minutes(10.), &air);
+    model.record(Depth::from_meters(30.), Time::from_minutes(10.), &air);
+    assert_close_to_abs!(model.average_depth().as_meters(), 20., 1e-9);
+
+    // travel segment contributes its integrated mean depth
+    let mut travel_model = fixtures::model_default();
+    travel_model.record_travel(Depth::from_meters(20.), Time::from_minutes(2.), &air);
+    assert_close_to_abs!(travel_model.average_depth().as_meters(), 10., 1e-9);
+    travel_model.record(Depth::from_meters(20.), Time::from_minutes(2.), &air);
+    assert_close_to_abs!(travel_model.average_depth().as_meters(), 15., 1e-9);
+}
+
+#[test]
+fn test_per_compartment_gf() {
+    let air = Gas::air();
+    let deco_stops = |per_compartment_gf: Option<[(u8, u8); 16]>| {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_per_compartment_gf(per_compartment_gf);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(40.), &air);
+        model
+            .deco(vec![air])
+            .unwrap()
+            .deco_stages
+            .into_iter()
+            .filter(|stage| stage.stage_type == DecoStageType::DecoStop)
+            .map(|stage| (stage.start_depth.as_meters(), stage.duration))
+            .collect::<Vec<_>>()
+    };
+
+    // same GF for all compartments equivalent to global GF
+    assert_eq!(deco_stops(Some([(30, 70); 16])), deco_stops(None));
+
+    // GF high on slower compartments (5-16) relaxed
+    let slow_compartments_gf = |gf| {
+        let mut per_compartment_gf = [(30, 70); 16];
+        for compartment_gf in per_compartment_gf.iter_mut().skip(4) {
+            *compartment_gf = gf;
+        }
+        Some(per_compartment_gf)
+    };
+    let stops = deco_stops(slow_compartments_gf((30, 75)));
+    let relaxed_stops = deco_stops(slow_compartments_gf((30, 90)));
+    assert_eq!(relaxed_stops.len(), stops.len());
+    for (relaxed_stop, stop) in relaxed_stops.iter().zip(stops.iter()) {
+        let (depth, relaxed_duration) = *relaxed_stop;
+        let (stop_depth, duration) = *stop;
+        assert_eq!(depth, stop_depth);
+        match depth > 12. {
+            // deep stops controlled by fast compartments
+            true => assert_eq!(relaxed_duration, duration),
+            false => assert!(relaxed_duration < duration),
+        }
+    }
+}
+
+#[test]
+fn test_gf_low_depth() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(20.), Time::from_minutes(10.), &air);
+    assert_eq!(model.gf_low_depth(), Depth::zero());
+
+    model.record(Depth::from_meters(50.), Time::from_minutes(20.), &air);
+    let gf_low_depth = model.gf_low_depth();
+    assert!(gf_low_depth > Depth::zero());
+
+    // first stop at GF low depth rounded up to 3m stop
+    let deco = model.deco(vec![air]).unwrap();
+    let first_stop = deco
+        .deco_stages
+        .iter()
+        .find(|stage| stage.stage_type == DecoStageType::DecoStop)
+        .unwrap();
+    let first_ascent = deco.deco_stages.first().unwrap();
+    assert_eq!(first_ascent.stage_type, DecoStageType::Ascent);
+    assert_eq!(first_ascent.end_depth, first_stop.start_depth);
+    assert_eq!(
+        first_stop.start_depth,
+        gf_low_depth.ceil_to(Depth::from_meters(3.))
+    );
+}
+
+#[test]
+fn test_raw_ceiling() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    let mut raw_model = fixtures::model_default();
+    for model in [&mut model, &mut raw_model] {
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    }
+    assert!(model.raw_ceiling() > Depth::zero());
+    assert!(model.raw_ceiling() < model.ceiling());
+    // same as configured ceiling at GF 100
+    assert_close_to_abs!(
+        model.raw_ceiling().as_meters(),
+        raw_model.ceiling().as_meters(),
+        1e-9
+    );
+}
+
+#[test]
+fn test_optimize_deco() {
+    let tmx = Gas::new(0.18, 0.45);
+    let ean_50 = Gas::new(0.5, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(60.), Time::from_minutes(20.), &tmx);
+
+    let candidate_gases = vec![tmx, ean_50, oxygen];
+    let default_runtime = model.deco(candidate_gases.clone()).unwrap();
+    let optimized_runtime = model.optimize_deco(candidate_gases).unwrap();
+    assert!(optimized_runtime.tts <= default_runtime.tts);
+    // deco gases shorten deco
+    let bottom_gas_runtime = model.deco(vec![tmx]).unwrap();
+    assert!(optimized_runtime.tts < bottom_gas_runtime.tts);
+    // gases used within MOD
+    let deco_pp_o2_limit = model.config().deco_pp_o2_limit;
+    for stage in optimized_runtime.deco_stages {
+        let gas_mod = stage.gas.max_operating_depth(deco_pp_o2_limit);
+        assert!(stage.start_depth.as_meters() <= gas_mod.as_meters() + 1e-6);
+    }
+}
+
+#[test]
+fn test_gf_low_reference() {
+    let air = Gas::air();
+    let gf_low_depth = |gf_low_reference| {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_gf_low_reference(gf_low_reference);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(50.), Time::from_minutes(20.), &air);
+        model.record(Depth::from_meters(30.), Time::zero(), &air);
+        model.gf_low_depth()
+    };
+
+    let max_supersaturation_depth = gf_low_depth(GfLowReference::MaxSupersaturation);
+    let first_stop_depth = gf_low_depth(GfLowReference::FirstStop);
+    assert_ne!(first_stop_depth, max_supersaturation_depth);
+    assert_eq!(
+        first_stop_depth,
+        max_supersaturation_depth.ceil_to(Depth::from_meters(3.))
+    );
+}
+
+#[test]
+fn test_pressure_group() {
+    let air = Gas::air();
+    let surfaced_model = |bottom_time: f64| {
+        let mut model = fixtures::model_default();
+        model.record(
+            Depth::from_meters(18.),
+            Time::from_minutes(bottom_time),
+            &air,
+        );
+        model.record(Depth::zero(), Time::zero(), &air);
+        model
+    };
+    assert_eq!(fixtures::model_default().pressure_group(), 'A');
+
+    let short_dive_group = surfaced_model(5.).pressure_group();
+    let long_dive_group = surfaced_model(40.).pressure_group();
+    assert!(short_dive_group <= 'J');
+    assert!(long_dive_group > short_dive_group);
+    assert!(long_dive_group <= 'Z');
+
+    // residual nitrogen off-gassed during surface interval
+    let mut model = surfaced_model(40.);
+    model.record(Depth::zero(), Time::from_minutes(60.), &air);
+    assert!(model.pressure_group() < long_dive_group);
+}
+
+#[test]
+fn test_ndl_fraction() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(30.), Time::zero(), &air);
+    assert_close_to_abs!(model.ndl_fraction(), 1., 1e-9);
+
+    let initial_ndl = model.ndl();
+    let mut last_fraction = model.ndl_fraction();
+    let mut bottom_time = Time::zero();
+    while bottom_time + Time::from_minutes(3.) < initial_ndl {
+        model.record(Depth::from_meters(30.), Time::from_minutes(3.), &air);
+        bottom_time += Time::from_minutes(3.);
+        let fraction = model.ndl_fraction();
+        assert!(fraction < last_fraction);
+        last_fraction = fraction;
+    }
+    assert!(last_fraction <= 0.25);
+
+    // in deco
+    model.record(Depth::from_meters(30.), Time::from_minutes(5.), &air);
+    assert_eq!(model.ndl_fraction(), 0.);
+}
+
+#[test]
+fn test_record_square() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record_square(Depth::from_meters(40.), 20., Time::from_minutes(25.), &air);
+
+    let mut manual_model = fixtures::model_gf((30, 70));
+    manual_model.record_travel_with_rate(Depth::from_meters(40.), 20., &air);
+    manual_model.record(Depth::from_meters(40.), Time::from_minutes(25.), &air);
+
+    assert_eq!(model, manual_model);
+    assert_eq!(model.dive_state().depth, Depth::from_meters(40.));
+    assert_eq!(model.dive_state().time, Time::from_minutes(27.));
+}
+
+#[test]
+fn test_leading_gradient() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    // on-gassing at depth
+    assert!(model.leading_gradient() < 0.);
+
+    // at ceiling, leading compartment at its M-value (GF 100)
+    let ceiling = model.ceiling();
+    model.record(ceiling, Time::zero(), &air);
+    let leading_comp = model
+        .tissues()
+        .into_iter()
+        .max_by(|a, b| {
+            a.min_tolerable_amb_pressure
+                .partial_cmp(&b.min_tolerable_amb_pressure)
+                .unwrap()
+        })
+        .unwrap();
+    let p_amb = ceiling.to_ambient_pressure(model.config().surface_pressure);
+    let gradient = model.leading_gradient();
+    assert!(gradient > 0.);
+    assert_close_to_abs!(gradient, leading_comp.m_value_raw - p_amb, 1e-9);
+    assert_close_to_abs!(model.supersaturation().gf_99, 100., 1e-6);
+}
+
+#[test]
+fn test_zhl_params() {
+    let model = fixtures::model_default();
+    assert_eq!(model.zhl_params(), ZHL_16C_N2_16A_HE_VALUES.to_vec());
+
+    let custom_model = BuehlmannModel::new_with_compartments(
+        BuehlmannConfig::default(),
+        &ZHL_16C_N2_16A_HE_VALUES[..4],
+    );
+    assert_eq!(
+        custom_model.zhl_params(),
+        ZHL_16C_N2_16A_HE_VALUES[..4].to_vec()
+    );
+}
+
+#[test]
+fn test_preview_descent() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(10.), Time::from_minutes(5.), &air);
+    let initial_state = model.dive_state();
+    let initial_supersaturation = model.supersaturation();
+
+    let preview =
+        model.preview_descent(Depth::from_meters(40.), 18., Time::from_minutes(20.), &air);
+
+    let mut sim_model = model.fork();
+    sim_model.record_travel_with_rate(Depth::from_meters(40.), 18., &air);
+    sim_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    assert_eq!(preview, sim_model.supersaturation());
+
+    // model untouched
+    let state = model.dive_state();
+    assert_eq!(state.depth, initial_state.depth);
+    assert_eq!(state.time, initial_state.time);
+    assert_eq!(model.supersaturation(), initial_supersaturation);
+}
+
+#[test]
+fn test_oxtox_during_travel() {
+    let ean50 = Gas::new(0.5, 0.);
+    let descent = |oxtox_during_travel: bool| {
+        let config = BuehlmannConfig::default().with_oxtox_during_travel(oxtox_during_travel);
+        let mut model = BuehlmannModel::new(config);
+        model.record_travel_with_rate(Depth::from_meters(21.), 10., &ean50);
+        model
+    };
+
+    let model = descent(true);
+    assert!(model.cns() > 0.);
+    assert!(model.otu() > 0.);
+
+    let model_without_travel_oxtox = descent(false);
+    assert_eq!(model_without_travel_oxtox.cns(), 0.);
+    assert_eq!(model_without_travel_oxtox.otu(), 0.);
+    // depth and tissues still updated
+    let state = model_without_travel_oxtox.dive_state();
+    assert_eq!(state.depth, Depth::from_meters(21.));
+    assert_eq!(state.time, model.dive_state().time);
+    assert_eq!(
+        model_without_travel_oxtox.supersaturation(),
+        model.supersaturation()
+    );
+}
+
+#[test]
+fn test_gf_surface_ceiling_type() {
+    let air = Gas::air();
+    let ceiling = |ceiling_type: CeilingType| {
+        let config = BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_ceiling_type(ceiling_type);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        model.ceiling()
+    };
+    let actual_ceiling = ceiling(CeilingType::Actual);
+    let adaptive_ceiling = ceiling(CeilingType::Adaptive);
+    let gf_surface_ceiling = ceiling(CeilingType::GfSurface);
+
+    // GF high at depth more permissive than GF low slope, off-gassing on ascent not considered
+    assert!(gf_surface_ceiling > Depth::zero());
+    assert!(gf_surface_ceiling < actual_ceiling);
+    assert!(adaptive_ceiling < actual_ceiling);
+    assert_ne!(gf_surface_ceiling, adaptive_ceiling);
+
+    // cleared exactly when surfacing GF within GF high
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_ceiling_type(CeilingType::GfSurface);
+    let mut model = BuehlmannModel::new(config);
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    model.record_travel_with_rate(Depth::from_meters(3.), 9., &air);
+    loop {
+        let gf_surf = model.supersaturation().gf_surf;
+        assert_eq!(model.ceiling() > Depth::zero(), gf_surf > 70.);
+        if gf_surf <= 70. {
+            break;
+        }
+        model.record(Depth::from_meters(3.), Time::from_minutes(1.), &air);
+    }
+}
+
+#[test]
+fn test_record_with_env() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    let mut env_model = fixtures::model_default();
+    let samples = [(20., 5., 18.5), (20., 10., 14.), (6., 3., 16.2)];
+    for (depth, time, temp_c) in samples {
+        model.record(Depth::from_meters(depth), Time::from_minutes(time), &air);
+        env_model.record_with_env(
+            Depth::from_meters(depth),
+            Time::from_minutes(time),
+            &air,
+            temp_c,
+        );
+    }
+
+    assert_eq!(
+        env_model.temperature_trace(),
+        &[
+            (Time::from_minutes(5.), 18.5),
+            (Time::from_minutes(15.), 14.),
+            (Time::from_minutes(18.), 16.2),
+        ]
+    );
+    assert!(model.temperature_trace().is_empty());
+    // deco math unaffected
+    assert_eq!(env_model.supersaturation(), model.supersaturation());
+    assert_eq!(env_model.ceiling(), model.ceiling());
+    assert_eq!(env_model.ndl(), model.ndl());
+    assert_eq!(env_model.cns(), model.cns());
+}
+
+#[test]
+fn test_reset_clock() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(30.), Time::from_minutes(25.), &air);
+    model.record_travel_with_rate(Depth::zero(), 9., &air);
+    let tissues = model.tissues();
+    let (cns, otu) = (model.cns(), model.otu());
+
+    model.reset_clock();
+    let state = model.dive_state();
+    assert_eq!(state.time, Time::zero());
+    assert_eq!(state.depth, Depth::zero());
+    assert_eq!(state.max_depth, Depth::zero());
+    assert_eq!(model.average_depth(), Depth::zero());
+    for (tissue, initial_tissue) in model.tissues().iter().zip(tissues.iter()) {
+        assert_eq!(tissue.total_ip, initial_tissue.total_ip);
+    }
+    assert_eq!(model.cns(), cns);
+    assert_eq!(model.otu(), otu);
+
+    // next dive runtime from zero
+    model.record(Depth::from_meters(18.), Time::from_minutes(10.), &air);
+    assert_eq!(model.dive_state().time, Time::from_minutes(10.));
+}
+
+#[test]
+fn test_cns_time_remaining() {
+    let ean50 = Gas::new(0.5, 0.);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::zero(), &ean50);
+    let initial_cns_clock = model.cns_time_remaining();
+    // ppO2 over 1.6, 100% CNS within configured limit
+    assert_close_to_abs!(initial_cns_clock.as_seconds(), 400., 1e-6);
+
+    model.record(Depth::from_meters(40.), Time::from_minutes(2.), &ean50);
+    let cns_clock = model.cns_time_remaining();
+    assert!(cns_clock < initial_cns_clock);
+    assert_close_to_abs!(cns_clock.as_seconds(), 280., 1e-6);
+
+    // CNS reaching 100% after remaining time
+    model.record(Depth::from_meters(40.), cns_clock, &ean50);
+    assert_close_to_abs!(model.cns(), 100., 1e-6);
+    assert_eq!(model.cns_time_remaining(), Time::zero());
+
+    // CNS not accruing at ppO2 below 0.5
+    let mut air_model = fixtures::model_default();
+    air_model.record(
+        Depth::from_meters(10.),
+        Time::from_minutes(10.),
+        &Gas::air(),
+    );
+    assert_eq!(air_model.cns_time_remaining(), Time::from_minutes(720.));
+}
+
+#[test]
+fn test_at_equilibrium() {
+    let air = Gas::air();
+    let depth = Depth::from_meters(40.);
+    let config = BuehlmannConfig::default();
+    let model = BuehlmannModel::at_equilibrium(config, depth, &air);
+
+    let state = model.dive_state();
+    assert_eq!(state.depth, depth);
+    assert_eq!(state.time, Time::zero());
+    let inspired_pp = air.inspired_partial_pressures(depth, config.surface_pressure);
+    for tissue in model.tissues() {
+        assert_close_to_abs!(tissue.n2_ip, inspired_pp.n2, 1e-9);
+        assert_close_to_abs!(tissue.total_ip, inspired_pp.n2 + inspired_pp.he, 1e-9);
+    }
+
+    // ceiling of fully saturated tissues, approached by long exposure
+    let mut long_exposure_model = BuehlmannModel::new(config);
+    long_exposure_model.record(depth, Time::from_minutes(72. * 60.), &air);
+    let ceiling = model.ceiling();
+    assert!(ceiling > Depth::zero());
+    assert!(ceiling >= long_exposure_model.ceiling());
+    assert_close_to_abs!(
+        ceiling.as_meters(),
+        long_exposure_model.ceiling().as_meters(),
+        0.5
+    );
+}
+
+#[test]
+fn test_per_compartment_gf_extended_compartments() {
+    let air = Gas::air();
+    let mut zhl_values = ZHL_16C_N2_16A_HE_VALUES.to_vec();
+    zhl_values.extend_from_slice(&ZHL_16C_N2_16A_HE_VALUES[12..]);
+    let config = BuehlmannConfig::new()
+        .with_gradient_factors(30, 70)
+        .with_per_compartment_gf(Some([(40, 80); 16]));
+    let mut model = BuehlmannModel::new_with_compartments(config, &zhl_values);
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    assert_eq!(model.tissues().len(), 20);
+    assert!(model.ceiling() > Depth::zero());
+    assert!(model.deco(vec![air]).is_ok());
+}
@@ -1,8 +1,12 @@
-use dive_deco::{DecoModel, Depth, Gas, Time};
+use dive_deco::{
+    BuehlmannConfig, BuehlmannModel, CNSCoeffRow, ConfigValidationErr, DecoModel, Depth, Gas, Time,
+    CNS_COEFFICIENTS,
+};
 
 pub mod fixtures;
 
 #[test]
+#[allow(clippy::unnecessary_cast)]
 fn test_cns() {
     let mut model = fixtures::model_default();
 
@@ -28,3 +32,109 @@ fn test_cns_multi_stage() {
     let cns = model.cns();
     assert_close_to_abs!(cns, 26., 1.);
 }
+
+#[test]
+fn test_cns_travel() {
+    let nitrox = Gas::new(0.32, 0.);
+    let target_depth = Depth::from_meters(40.);
+    let descent_time = Time::from_minutes(4.);
+
+    let mut travel_model = fixtures::model_default();
+    travel_model.record_travel(target_depth, descent_time, &nitrox);
+
+    // manual per-second descent
+    let mut manual_model = fixtures::model_default();
+    let rate = target_depth.as_meters() / descent_time.as_seconds();
+    let mut depth = Depth::zero();
+    for _ in 0..(descent_time.as_seconds() as i32) {
+        depth += Depth::from_meters(rate);
+        manual_model.record(depth, Time::from_seconds(1.), &nitrox);
+    }
+    assert_close_to_abs!(travel_model.cns(), manual_model.cns(), 1e-9);
+    assert_close_to_abs!(travel_model.otu(), manual_model.otu(), 1e-9);
+
+    // descent spent partially on lower ppO2 compared to instant drop
+    let mut drop_model = fixtures::model_default();
+    drop_model.record(target_depth, descent_time, &nitrox);
+    assert!(travel_model.cns() > 0.);
+    assert!(travel_model.cns() < drop_model.cns());
+
+    let bottom_time = Time::from_minutes(16.);
+    travel_model.record(target_depth, bottom_time, &nitrox);
+    drop_model.record(target_depth, bottom_time, &nitrox);
+    assert!(travel_model.cns() < drop_model.cns());
+}
+
+#[test]
+fn test_cns_limit_over_max_pp_o2() {
+    let ean_50 = Gas::new(0.5, 0.);
+    let mut default_model = fixtures::model_default();
+    let mut custom_limit_model = BuehlmannModel::new(
+        BuehlmannConfig::default().with_cns_limit_over_max_pp_o2(Time::from_seconds(200.)),
+    );
+    default_model.record(Depth::from_meters(30.), Time::from_seconds(200.), &ean_50);
+    custom_limit_model.record(Depth::from_meters(30.), Time::from_seconds(200.), &ean_50);
+    assert_close_to_abs!(default_model.cns(), 50., 0.5);
+    assert_close_to_abs!(custom_limit_model.cns(), 100., 1.);
+}
+
+#[test]
+fn test_cns_surface_interval_gas() {
+    let ean_32 = Gas::new(0.32, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(30.), Time::from_minutes(30.), &ean_32);
+    model.record_travel_with_rate(Depth::zero(), 9., &ean_32);
+    let initial_cns = model.cns();
+
+    let mut air_interval_model = model.clone();
+    air_interval_model.surface_interval(Time::from_minutes(30.), &Gas::air());
+    assert!(air_interval_model.cns() < initial_cns);
+
+    let mut o2_interval_model = model.clone();
+    o2_interval_model.surface_interval(Time::from_minutes(30.), &oxygen);
+    assert!(o2_interval_model.cns() > initial_cns);
+}
+
+#[test]
+fn test_custom_cns_table() {
+    let ean_32 = Gas::new(0.32, 0.);
+    // stricter table - time limits halved
+    let strict_cns_table: Vec<CNSCoeffRow> = CNS_COEFFICIENTS
+        .iter()
+        .map(|(range, slope, intercept)| (range.clone(), slope / 2, intercept / 2))
+        .collect();
+    let mut default_model = fixtures::model_default();
+    let mut strict_model = fixtures::model_default();
+    assert_eq!(strict_model.set_cns_table(strict_cns_table.clone()), Ok(()));
+    assert_eq!(strict_model.cns_table(), strict_cns_table.as_slice());
+
+    default_model.record(Depth::from_meters(30.), Time::from_minutes(30.), &ean_32);
+    strict_model.record(Depth::from_meters(30.), Time::from_minutes(30.), &ean_32);
+    assert!(default_model.cns() > 0.);
+    assert_close_to_percent!(strict_model.cns(), default_model.cns() * 2., 0.01);
+}
+
+#[test]
+fn test_invalid_cns_table() {
+    let mut model = fixtures::model_default();
+    let invalid_tables: Vec<Vec<CNSCoeffRow>> = vec![
+        vec![],
+        // gap
+        vec![(0.5..=0.6, -1800, 1800), (0.7..=0.8, -1200, 1410)],
+        // overlap
+        vec![(0.5..=0.7, -1800, 1800), (0.6..=0.8, -1200, 1410)],
+        // empty range
+        vec![(0.6..=0.6, -1800, 1800)],
+    ];
+    for invalid_table in invalid_tables {
+        assert_eq!(
+            model.set_cns_table(invalid_table),
+            Err(ConfigValidationErr::new(
+                "cns_table",
+                "CNS table ppO2 ranges must be ascending, contiguous and non-overlapping"
+            ))
+        );
+    }
+    assert_eq!(model.cns_table(), CNS_COEFFICIENTS.as_slice());
+}
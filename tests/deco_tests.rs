@@ -1,6 +1,10 @@
+use std::{cell::Cell, rc::Rc};
+
 use dive_deco::{
-    BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel, DecoRuntime, DecoStage, DecoStageType,
-    Depth, Gas, Time,
+    BuehlmannConfig, BuehlmannModel, CeilingType, Deco, DecoCalculationError, DecoDiff, DecoModel,
+    DecoRuntime, DecoSession, DecoStage, DecoStageType, DecoStopDiff, Depth, DiveState, Gas,
+    GasPlanEntry, GasSelectionOutcome, GasSelectionReason, ProfileSegment, SafetyStop, Sim,
+    StickyDecoPlan, StopRounding, Time,
 };
 
 pub mod fixtures;
@@ -278,6 +282,1038 @@ fn test_deco_runtime_integrity() {
     });
 }
 
+#[test]
+fn test_deco_with_approx_equal_current_gas() {
+    let mut model = fixtures::model_gf((30, 70));
+    let air = Gas::air();
+    // rounded fractions sum, not bitwise equal to model's air
+    let air_from_fractions = Gas::new(0.1 + 0.11, 0.);
+    let ean_50 = Gas::new(0.5, 0.);
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let deco_res = model.deco(vec![air_from_fractions, ean_50]);
+    assert!(deco_res.is_ok());
+    assert_eq!(
+        deco_res.unwrap().tts,
+        model.deco(vec![air, ean_50]).unwrap().tts
+    );
+}
+
+#[test]
+fn test_min_gas() {
+    let mut model = fixtures::model_gf((30, 70));
+    let air = Gas::air();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let min_gas = model.min_gas(30., vec![air]).unwrap();
+    // direct ascent only: 4 mins at mean ambient pressure of 20m
+    let direct_ascent_gas = 30. * 4. * (1.013 + 2.);
+    assert!(min_gas > direct_ascent_gas);
+    assert!(min_gas < 3000., "rock bottom {min_gas}l");
+
+    let min_gas_doubled_sac = model.min_gas(60., vec![air]).unwrap();
+    assert_close_to_abs!(min_gas_doubled_sac, min_gas * 2., 1e-9);
+}
+
+#[test]
+fn test_deco_runtime_diff() {
+    let mut model = BuehlmannModel::new(BuehlmannConfig::default().with_deco_ascent_rate(9.));
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let single_gas_runtime = model.deco(vec![air]).unwrap();
+    let multi_gas_runtime = model.deco(vec![air, ean_50]).unwrap();
+    let DecoDiff { tts_delta, stops } = single_gas_runtime.diff(&multi_gas_runtime);
+
+    assert_eq!(tts_delta, single_gas_runtime.tts - multi_gas_runtime.tts);
+    assert_eq!(
+        stops,
+        vec![
+            DecoStopDiff {
+                depth: Depth::from_meters(6.),
+                duration: Time::from_seconds(88.),
+                other_duration: Time::from_seconds(34.),
+                duration_delta: Time::from_seconds(54.),
+            },
+            DecoStopDiff {
+                depth: Depth::from_meters(3.),
+                duration: Time::from_seconds(400.),
+                other_duration: Time::from_seconds(291.),
+                duration_delta: Time::from_seconds(109.),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_first_stop_depth() {
+    let air = Gas::air();
+
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let first_stop_depth = model.first_stop_depth(vec![air]).unwrap();
+    assert_eq!(first_stop_depth, Some(Depth::from_meters(9.)));
+    assert_eq!(
+        first_stop_depth,
+        get_first_deco_stop_depth(model.deco(vec![air]).unwrap())
+    );
+
+    let mut no_deco_model = fixtures::model_default();
+    no_deco_model.record(Depth::from_meters(12.), Time::from_minutes(20.), &air);
+    assert_eq!(no_deco_model.first_stop_depth(vec![air]).unwrap(), None);
+}
+
+#[test]
+fn test_time_to_first_stop() {
+    let air = Gas::air();
+
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let first_stop_depth = model.first_stop_depth(vec![air]).unwrap().unwrap();
+    let ascent_rate = model.config().deco_ascent_rate;
+    let expected_time =
+        Time::from_minutes((Depth::from_meters(40.) - first_stop_depth).as_meters() / ascent_rate);
+    let time_to_first_stop = model.time_to_first_stop(vec![air]).unwrap().unwrap();
+    assert_close_to_abs!(
+        time_to_first_stop.as_seconds(),
+        expected_time.as_seconds(),
+        1.
+    );
+
+    let mut no_deco_model = fixtures::model_default();
+    no_deco_model.record(Depth::from_meters(12.), Time::from_minutes(20.), &air);
+    assert_eq!(no_deco_model.time_to_first_stop(vec![air]).unwrap(), None);
+}
+
+#[test]
+fn test_safety_stop() {
+    let air = Gas::air();
+    let safety_stop = SafetyStop {
+        depth: Depth::from_meters(5.),
+        time: Time::from_minutes(3.),
+        required_above_depth: Depth::from_meters(10.),
+    };
+    let config = BuehlmannConfig::default().with_safety_stop(safety_stop);
+
+    // no-deco dive
+    let mut model = BuehlmannModel::new(config);
+    model.record(Depth::from_meters(20.), Time::from_minutes(25.), &air);
+    let DecoRuntime {
+        deco_stages, tts, ..
+    } = model.deco(vec![air]).unwrap();
+    let expected_deco_stages = vec![
+        DecoStage {
+            stage_type: DecoStageType::Ascent,
+            start_depth: Depth::from_meters(20.),
+            end_depth: Depth::from_meters(5.),
+            duration: Time::from_seconds(90.),
+            gas: air,
+        },
+        DecoStage {
+            stage_type: DecoStageType::SafetyStop,
+            start_depth: Depth::from_meters(5.),
+            end_depth: Depth::from_meters(5.),
+            duration: Time::from_minutes(3.),
+            gas: air,
+        },
+        DecoStage {
+            stage_type: DecoStageType::Ascent,
+            start_depth: Depth::from_meters(5.),
+            end_depth: Depth::zero(),
+            duration: Time::from_seconds(30.),
+            gas: air,
+        },
+    ];
+    assert_deco_stages_eq(deco_stages, expected_deco_stages);
+    assert_eq!(tts, Time::from_minutes(5.));
+    // safety stop not reported as deco stop
+    assert!(!model.in_deco());
+    assert_eq!(model.first_stop_depth(vec![air]).unwrap(), None);
+    assert_eq!(model.time_to_first_stop(vec![air]).unwrap(), None);
+    let mut sticky_plan = StickyDecoPlan::new(model.clone(), vec![air]).unwrap();
+    sticky_plan.record_travel_with_rate(Depth::from_meters(3.), 9., &air);
+    assert!(sticky_plan.completed_stops().is_empty());
+
+    // shallow dive, not required
+    let mut shallow_model = BuehlmannModel::new(config);
+    shallow_model.record(Depth::from_meters(8.), Time::from_minutes(25.), &air);
+    let shallow_runtime = shallow_model.deco(vec![air]).unwrap();
+    assert_eq!(shallow_runtime.deco_stages.len(), 1);
+
+    // mandatory deco dive
+    let mut deco_model = BuehlmannModel::new(config);
+    deco_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let deco_runtime = deco_model.deco(vec![air]).unwrap();
+    let mut reference_model = fixtures::model_default();
+    reference_model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let reference_runtime = reference_model.deco(vec![air]).unwrap();
+    assert_eq!(deco_runtime.deco_stages, reference_runtime.deco_stages);
+    assert!(!deco_runtime
+        .deco_stages
+        .iter()
+        .any(|stage| stage.start_depth == Depth::from_meters(5.)));
+}
+
+#[test]
+fn test_ascend_step_replan() {
+    let mut model = fixtures::model_gf((30, 70));
+    let air = Gas::air();
+    let step = Depth::from_meters(3.);
+    model.record(Depth::from_meters(45.), Time::from_minutes(30.), &air);
+
+    let mut prev_tts = model.deco(vec![air]).unwrap().tts;
+    loop {
+        let depth = model.dive_state().depth;
+        if depth <= Depth::zero() {
+            break;
+        }
+        let mut next_depth = depth - step;
+        if next_depth < Depth::zero() {
+            next_depth = Depth::zero();
+        }
+        let runtime = match model.ceiling() > next_depth {
+            // stop not cleared yet
+            true => {
+                model.record(depth, Time::from_minutes(1.), &air);
+                model.deco(vec![air]).unwrap()
+            }
+            false => model.ascend_step(next_depth, 10., vec![air]).unwrap(),
+        };
+        assert!(
+            runtime.tts < prev_tts,
+            "TTS should decrease ({}s at {}m, previously {}s)",
+            runtime.tts.as_seconds(),
+            model.dive_state().depth.as_meters(),
+            prev_tts.as_seconds()
+        );
+        prev_tts = runtime.tts;
+    }
+    assert_eq!(prev_tts, Time::zero());
+}
+
+#[test]
+fn test_gas_density_limit() {
+    let tmx_15_55 = Gas::trimix(0.15, 0.55);
+    let ean_32 = Gas::ean32();
+    let tmx_32_25 = Gas::trimix(0.32, 0.25);
+    let ean_50 = Gas::ean50();
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_gas_density_limit(5.7);
+    let mut model = BuehlmannModel::new(config);
+    model.record(Depth::from_meters(60.), Time::from_minutes(20.), &tmx_15_55);
+
+    let DecoRuntime { deco_stages, .. } = model
+        .deco(vec![tmx_15_55, ean_32, tmx_32_25, ean_50])
+        .unwrap();
+    let switch_gases: Vec<Gas> = deco_stages
+        .iter()
+        .filter(|stage| stage.stage_type == DecoStageType::GasSwitch)
+        .map(|stage| stage.gas)
+        .collect();
+    assert_eq!(switch_gases, vec![tmx_32_25, ean_50]);
+}
+
+#[test]
+fn test_deco_profile_samples() {
+    let air = Gas::air();
+    let ean_50 = Gas::ean50();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let interval = Time::from_seconds(30.);
+    let DecoRuntime { tts, .. } = model.deco(vec![air, ean_50]).unwrap();
+    let samples = model
+        .deco_profile_samples(vec![air, ean_50], interval)
+        .unwrap();
+
+    let first_sample = samples.first().unwrap();
+    let last_sample = samples.last().unwrap();
+    assert_eq!(first_sample.time, Time::from_minutes(20.));
+    assert_eq!(first_sample.depth, Depth::from_meters(40.));
+    assert_eq!(last_sample.depth, Depth::zero());
+    assert_close_to_abs!(
+        (last_sample.time - first_sample.time).as_seconds(),
+        tts.as_seconds(),
+        1e-6
+    );
+    samples.windows(2).for_each(|pair| {
+        let time_delta = pair[1].time - pair[0].time;
+        assert!(time_delta > Time::zero());
+        assert!(time_delta <= interval);
+        assert!(pair[1].depth <= pair[0].depth);
+    });
+    assert!(samples.iter().any(|sample| sample.gas == ean_50));
+}
+
+#[test]
+fn test_deco_profile_samples_invalid_interval() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let samples = model.deco_profile_samples(vec![air], Time::zero());
+    assert_eq!(
+        samples,
+        Err(DecoCalculationError::InvalidSamplingInterval {
+            interval: Time::zero()
+        })
+    );
+}
+
+#[test]
+fn test_deco_pp_o2_limit() {
+    let air = Gas::air();
+    let ean_50 = Gas::ean50();
+    let gas_switch_depth = |config: BuehlmannConfig| {
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+        let DecoRuntime { deco_stages, .. } = model.deco(vec![air, ean_50]).unwrap();
+        deco_stages
+            .into_iter()
+            .find(|stage| stage.stage_type == DecoStageType::GasSwitch)
+            .map(|stage| stage.start_depth)
+    };
+
+    let default_config = BuehlmannConfig::default().with_deco_ascent_rate(9.);
+    assert_eq!(
+        gas_switch_depth(default_config),
+        Some(Depth::from_meters(22.))
+    );
+    assert_eq!(
+        gas_switch_depth(default_config.with_deco_pp_o2_limit(1.5)),
+        Some(Depth::from_meters(20.))
+    );
+}
+
+#[test]
+fn test_controlling_compartment_trace() {
+    let tmx = Gas::trimix(0.18, 0.45);
+    let ean_50 = Gas::ean50();
+    let oxygen = Gas::oxygen();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(60.), Time::from_minutes(25.), &tmx);
+
+    let trace = model
+        .controlling_compartment_trace(vec![tmx, ean_50, oxygen], Time::from_minutes(1.))
+        .unwrap();
+    let (_, initial_leading_comp) = trace.first().unwrap();
+    let (_, final_leading_comp) = trace.last().unwrap();
+    // fast compartments leading on the bottom, slower ones on shallow stops
+    assert!(*initial_leading_comp <= 3);
+    assert!(*final_leading_comp >= 8);
+    assert!(trace.windows(2).all(|pair| pair[1].0 > pair[0].0));
+}
+
+#[test]
+fn test_deco_runtime_to_csv() {
+    // test_deco_single_gas schedule
+    let air = fixtures::gas_air();
+    let mut model = BuehlmannModel::new(BuehlmannConfig::default().with_deco_ascent_rate(9.));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let deco_runtime = model.deco(vec![air]).unwrap();
+
+    let csv = deco_runtime.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("stage_type,start_depth_m,end_depth_m,duration_s,gas")
+    );
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[0], vec!["ascent", "40", "6", "226", "21/0"]);
+    assert_eq!(rows[3], vec!["deco_stop", "3", "3", "400", "21/0"]);
+    let csv_tts: f64 = rows.iter().map(|row| row[3].parse::<f64>().unwrap()).sum();
+    assert_eq!(Time::from_seconds(csv_tts), Time::from_seconds(754.));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_deco_runtime_to_json() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let deco_runtime = model.deco(vec![air]).unwrap();
+    let json = deco_runtime.to_json();
+    assert!(json.starts_with("{\"deco_stages\":[{\"stage_type\":\"Ascent\""));
+}
+
+#[test]
+fn test_first_stop_rounding() {
+    let air = Gas::air();
+    let first_stop = |first_stop_rounding: StopRounding| {
+        let config = BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_first_stop_rounding(first_stop_rounding);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(50.), Time::from_minutes(30.), &air);
+        model.first_stop_depth(vec![air]).unwrap().unwrap()
+    };
+
+    assert_eq!(first_stop(StopRounding::Up), Depth::from_meters(24.));
+    assert_eq!(
+        first_stop(StopRounding::ToGfLowDepth),
+        Depth::from_meters(27.)
+    );
+    assert_eq!(first_stop(StopRounding::Nearest), Depth::from_meters(24.));
+}
+
+#[test]
+fn test_switch_at_stops_only() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let gas_switch_depths = |switch_at_stops_only: bool| {
+        let config = BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_switch_at_stops_only(switch_at_stops_only);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        let deco = model.deco(vec![air, ean_50, oxygen]).unwrap();
+        deco.deco_stages
+            .into_iter()
+            .filter(|stage| stage.stage_type == DecoStageType::GasSwitch)
+            .map(|stage| stage.start_depth)
+            .collect::<Vec<Depth>>()
+    };
+
+    // default - mid-water switch at EAN50 MOD
+    assert_eq!(gas_switch_depths(false)[0], Depth::from_meters(22.));
+    // switch at stops only
+    let stop_switch_depths = gas_switch_depths(true);
+    assert_eq!(
+        stop_switch_depths,
+        vec![Depth::from_meters(21.), Depth::from_meters(6.)]
+    );
+    for switch_depth in stop_switch_depths {
+        assert_eq!(switch_depth.as_meters() % 3., 0.);
+    }
+}
+
+#[test]
+fn test_gas_plan() {
+    let mut model = BuehlmannModel::new(BuehlmannConfig::default().with_deco_ascent_rate(9.));
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.50, 0.);
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    let deco_runtime = model.deco(vec![air, ean_50]).unwrap();
+    let gas_plan = deco_runtime.gas_plan();
+
+    assert_eq!(
+        gas_plan,
+        vec![
+            GasPlanEntry {
+                gas: air,
+                switch_depth: Depth::zero(),
+                total_time: Time::from_seconds(120.),
+            },
+            GasPlanEntry {
+                gas: ean_50,
+                switch_depth: Depth::from_meters(22.),
+                total_time: Time::from_seconds(471.),
+            },
+        ]
+    );
+    let total_time = gas_plan
+        .iter()
+        .fold(Time::zero(), |acc, entry| acc + entry.total_time);
+    assert_eq!(total_time, deco_runtime.tts);
+}
+
+#[test]
+fn test_deco_session() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.50, 0.);
+    let gas_mixes = vec![air, ean_50];
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let mut session = DecoSession::new(model, gas_mixes.clone(), Depth::from_meters(1.));
+
+    // initial calculation
+    let runtime = session.runtime().unwrap();
+    assert_eq!(runtime, session.model().deco(gas_mixes.clone()).unwrap());
+    assert_eq!(session.recalculations(), 1);
+
+    // static within threshold - cached runtime
+    for _ in 0..5 {
+        session.record(Depth::from_meters(40.), Time::from_seconds(1.), &air);
+        assert_eq!(session.runtime().unwrap(), runtime);
+    }
+    assert_eq!(session.recalculations(), 1);
+
+    // ascent beyond threshold - recalculated
+    session.record_travel_with_rate(Depth::from_meters(21.), 10., &air);
+    let runtime = session.runtime().unwrap();
+    assert_eq!(session.recalculations(), 2);
+    assert_eq!(runtime, session.model().deco(gas_mixes.clone()).unwrap());
+
+    // gas switch - recalculated
+    session.record(Depth::from_meters(21.), Time::zero(), &ean_50);
+    let runtime = session.runtime().unwrap();
+    assert_eq!(session.recalculations(), 3);
+    assert_eq!(runtime, session.model().deco(gas_mixes).unwrap());
+}
+
+#[test]
+fn test_deco_session_ndl_boundary() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(30.), Time::zero(), &air);
+    let ndl = model.ndl();
+    model.record(Depth::from_meters(30.), ndl, &air);
+    assert_eq!(model.ceiling(), Depth::zero());
+    let mut session = DecoSession::new(model, vec![air], Depth::from_meters(1.));
+    let no_deco_runtime = session.runtime().unwrap();
+
+    // static diver entering deco, ceiling change within threshold
+    let has_deco_stop = |runtime: &DecoRuntime| {
+        runtime
+            .deco_stages
+            .iter()
+            .any(|stage| stage.stage_type == DecoStageType::DecoStop)
+    };
+    while !has_deco_stop(&session.model().deco(vec![air]).unwrap()) {
+        session.record(Depth::from_meters(30.), Time::from_seconds(10.), &air);
+    }
+    assert!(session.model().ceiling() < Depth::from_meters(1.));
+    let runtime = session.runtime().unwrap();
+    assert_eq!(session.recalculations(), 2);
+    assert_eq!(runtime, session.model().deco(vec![air]).unwrap());
+    assert!(!has_deco_stop(&no_deco_runtime));
+}
+
+#[test]
+fn test_sticky_deco_plan() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let mut plan = StickyDecoPlan::new(model, vec![air]).unwrap();
+    let initial_stops: Vec<DecoStage> = plan
+        .initial_runtime()
+        .deco_stages
+        .iter()
+        .filter(|stage| stage.stage_type == DecoStageType::DecoStop)
+        .copied()
+        .collect();
+    assert!(initial_stops.len() > 3);
+    assert!(plan.completed_stops().is_empty());
+
+    // ascent through two stops, each served in full
+    for stop in initial_stops[..2].iter() {
+        plan.record_travel_with_rate(stop.start_depth, 10., &air);
+        plan.record(stop.start_depth, stop.duration, &air);
+    }
+    plan.record_travel_with_rate(initial_stops[2].start_depth, 10., &air);
+    assert_eq!(plan.completed_stops(), &initial_stops[..2]);
+
+    // completed stops don't reappear in sticky runtime
+    let current_depth = plan.model().dive_state().depth;
+    let is_passed_stop = |stage: &DecoStage| {
+        stage.stage_type == DecoStageType::DecoStop && stage.start_depth > current_depth
+    };
+    let runtime = plan.runtime().unwrap();
+    assert!(!runtime.deco_stages.iter().any(is_passed_stop));
+    let sticky_stops_time = runtime
+        .deco_stages
+        .iter()
+        .fold(Time::zero(), |acc, stage| acc + stage.duration);
+    assert_eq!(runtime.tts, sticky_stops_time);
+}
+
+#[test]
+fn test_sticky_deco_plan_missed_stops() {
+    let air = Gas::air();
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let mut plan = StickyDecoPlan::new(model, vec![air]).unwrap();
+    let first_stop = plan
+        .initial_runtime()
+        .deco_stages
+        .iter()
+        .find(|stage| stage.stage_type == DecoStageType::DecoStop)
+        .copied()
+        .unwrap();
+
+    // first stop left early, not completed
+    plan.record_travel_with_rate(first_stop.start_depth, 10., &air);
+    let half_stop_time = Time::from_seconds(first_stop.duration.as_seconds() / 2.);
+    plan.record(first_stop.start_depth, half_stop_time, &air);
+    plan.record_travel_with_rate(first_stop.start_depth - Depth::from_meters(3.), 10., &air);
+    assert!(plan.completed_stops().is_empty());
+
+    // direct ascent past remaining stops, required stops kept in sticky runtime
+    plan.record_travel_with_rate(Depth::from_meters(3.), 10., &air);
+    assert!(plan.completed_stops().is_empty());
+    let plain_runtime = plan.model().deco(vec![air]).unwrap();
+    let runtime = plan.runtime().unwrap();
+    assert!(plan.model().ceiling() > Depth::from_meters(3.));
+    assert_eq!(runtime, plain_runtime);
+}
+
+#[test]
+fn test_min_stop_time() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.50, 0.);
+    let deco_runtime = |min_stop_time: Time| {
+        let config = BuehlmannConfig::default()
+            .with_deco_ascent_rate(9.)
+            .with_min_stop_time(min_stop_time);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+        model.deco(vec![air, ean_50]).unwrap()
+    };
+
+    // 34s stop at 6m by default
+    let default_runtime = deco_runtime(Time::zero());
+    assert!(default_runtime
+        .deco_stages
+        .iter()
+        .any(|stage| stage.stage_type == DecoStageType::DecoStop
+            && stage.duration < Time::from_minutes(1.)));
+
+    let runtime = deco_runtime(Time::from_minutes(1.));
+    let deco_stops: Vec<&DecoStage> = runtime
+        .deco_stages
+        .iter()
+        .filter(|stage| stage.stage_type == DecoStageType::DecoStop)
+        .collect();
+    assert_eq!(deco_stops.len(), 2);
+    for deco_stop in deco_stops {
+        assert!(deco_stop.duration >= Time::from_minutes(1.));
+    }
+    assert!(runtime.tts > default_runtime.tts);
+}
+
+#[test]
+fn test_at_deco_stop() {
+    let air = Gas::air();
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let stop_depth = model.first_stop_depth(vec![air]).unwrap().unwrap();
+    assert!(!model.at_deco_stop(vec![air]));
+
+    // 2m below stop
+    let mut below_stop_model = model.clone();
+    below_stop_model.record_travel_with_rate(stop_depth + Depth::from_meters(2.), 10., &air);
+    assert!(!below_stop_model.at_deco_stop(vec![air]));
+
+    // at stop
+    model.record_travel_with_rate(stop_depth, 10., &air);
+    assert!(model.at_deco_stop(vec![air]));
+
+    // no deco
+    let mut no_deco_model = fixtures::model_default();
+    no_deco_model.record(Depth::from_meters(12.), Time::from_minutes(20.), &air);
+    assert!(!no_deco_model.at_deco_stop(vec![air]));
+}
+
+#[test]
+fn test_run_list() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.50, 0.);
+    let mut model = fixtures::model_default();
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    let deco_runtime = model.deco(vec![air, ean_50]).unwrap();
+    let start_time = model.dive_state().time;
+
+    let run_list = deco_runtime.run_list(start_time);
+    assert_eq!(run_list.len(), deco_runtime.deco_stages.len());
+    let first_entry = run_list.first().unwrap();
+    assert_eq!(first_entry.stage_type, DecoStageType::Ascent);
+    assert_eq!(first_entry.run_time, start_time + first_entry.duration);
+    let last_entry = run_list.last().unwrap();
+    assert_eq!(last_entry.depth, Depth::zero());
+    assert_eq!(last_entry.run_time, start_time + deco_runtime.tts);
+}
+
+#[test]
+fn test_solve_gf_high_for_surface() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.50, 0.);
+    let mut model = fixtures::model_gf((30, 85));
+    model.record(Depth::from_meters(40.), Time::from_minutes(25.), &air);
+
+    let gf_high = model
+        .solve_gf_high_for_surface(70., vec![air, ean_50])
+        .unwrap();
+    assert!((30..=100).contains(&gf_high));
+
+    let mut solved_model = fixtures::model_gf((30, gf_high));
+    solved_model.record(Depth::from_meters(40.), Time::from_minutes(25.), &air);
+    let deco_stages = solved_model.deco(vec![air, ean_50]).unwrap().deco_stages;
+    let segments: Vec<ProfileSegment> = deco_stages
+        .iter()
+        .map(|stage| ProfileSegment {
+            depth: stage.end_depth,
+            time: stage.duration,
+            gas: stage.gas,
+        })
+        .collect();
+    let surface_gf = solved_model.simulate_surface_gf(&segments);
+    assert_close_to_abs!(surface_gf, 70., 1.);
+}
+
+#[test]
+fn test_cached_adaptive_first_stop() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let gas_mixes = vec![air, ean_50];
+    let config = BuehlmannConfig::default().with_gradient_factors(30, 70);
+    let mut adaptive_model = BuehlmannModel::new(config.with_ceiling_type(CeilingType::Adaptive));
+    let mut cached_adaptive_model =
+        BuehlmannModel::new(config.with_ceiling_type(CeilingType::CachedAdaptive));
+    adaptive_model.record(Depth::from_meters(50.), Time::from_minutes(20.), &air);
+    cached_adaptive_model.record(Depth::from_meters(50.), Time::from_minutes(20.), &air);
+
+    // fully adaptive first stop depth (ceiling considering off-gassing on ascent)
+    let stop_window = Depth::from_meters(3.);
+    let adaptive_first_stop = adaptive_model.ceiling().ceil_to(stop_window);
+    let cached_adaptive_first_stop =
+        get_first_deco_stop_depth(cached_adaptive_model.deco(gas_mixes.clone()).unwrap()).unwrap();
+    assert!(
+        (cached_adaptive_first_stop - adaptive_first_stop)
+            .as_meters()
+            .abs()
+            <= 3.
+    );
+    // shallower than first stop by actual ceiling
+    let mut actual_model = fixtures::model_gf((30, 70));
+    actual_model.record(Depth::from_meters(50.), Time::from_minutes(20.), &air);
+    let actual_first_stop =
+        get_first_deco_stop_depth(actual_model.deco(gas_mixes).unwrap()).unwrap();
+    assert!(cached_adaptive_first_stop < actual_first_stop);
+    // current ceiling unaffected
+    assert!(cached_adaptive_model.ceiling() > adaptive_model.ceiling());
+}
+
+#[test]
+fn test_deco_decisions_on_jittery_depth() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_switch_at_stops_only(true);
+    let mut model = BuehlmannModel::new(config);
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    // stop depth with float jitter (e.g. accumulated from travel integration)
+    let jittery_stop_depth = Depth::from_meters(21. + 1e-9);
+    model.record(jittery_stop_depth, Time::zero(), &air);
+
+    // switch at stop (within depth epsilon) without air stop or zero-length ascent
+    let deco = model.deco(vec![air, ean_50]).unwrap();
+    let first_stage = &deco.deco_stages[0];
+    assert_eq!(first_stage.stage_type, DecoStageType::GasSwitch);
+    assert!(first_stage.start_depth.approx_eq(Depth::from_meters(21.)));
+    assert_eq!(first_stage.gas, ean_50);
+    assert!(
+        deco.deco_stages
+            .iter()
+            .all(|stage| stage.stage_type == DecoStageType::GasSwitch
+                || stage.duration > Time::zero())
+    );
+}
+
+#[test]
+fn test_deco_auto_includes_current_gas() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+
+    assert_eq!(
+        model.deco(vec![ean_50]),
+        Err(DecoCalculationError::CurrentGasNotInList)
+    );
+    let auto_deco = model.deco_auto(vec![ean_50]).unwrap();
+    assert_eq!(auto_deco, model.deco(vec![air, ean_50]).unwrap());
+    assert_eq!(auto_deco.deco_stages[0].gas, air);
+}
+
+#[test]
+fn test_no_breathable_gas_at_stop() {
+    let hypoxic_trimix = Gas::new(0.1, 0.7);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(
+        Depth::from_meters(80.),
+        Time::from_minutes(20.),
+        &hypoxic_trimix,
+    );
+
+    let deco = model.deco(vec![hypoxic_trimix]);
+    assert_eq!(
+        deco,
+        Err(DecoCalculationError::NoBreathableGasAtStop {
+            depth: Depth::from_meters(6.)
+        })
+    );
+    // shallow stop served by deco gas
+    let ean_50 = Gas::new(0.5, 0.);
+    assert!(model.deco(vec![hypoxic_trimix, ean_50]).is_ok());
+}
+
+#[test]
+fn test_oxygen_window_stop() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let gas_mixes = vec![air, ean_50];
+    let runtime = |oxygen_window_stop: Time| {
+        let config = BuehlmannConfig::default()
+            .with_gradient_factors(30, 70)
+            .with_oxygen_window_stop(oxygen_window_stop);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+        model.deco(gas_mixes.clone()).unwrap()
+    };
+    let default_runtime = runtime(Time::zero());
+    let window_stop = Time::from_minutes(2.);
+    let window_stop_runtime = runtime(window_stop);
+
+    // stop at switch depth on new gas right after switch
+    let switch_index = window_stop_runtime
+        .deco_stages
+        .iter()
+        .position(|stage| stage.stage_type == DecoStageType::GasSwitch)
+        .unwrap();
+    let switch_stage = &window_stop_runtime.deco_stages[switch_index];
+    let window_stop_stage = &window_stop_runtime.deco_stages[switch_index + 1];
+    assert_eq!(window_stop_stage.stage_type, DecoStageType::DecoStop);
+    assert_eq!(window_stop_stage.start_depth, switch_stage.end_depth);
+    assert_eq!(window_stop_stage.start_depth, Depth::from_meters(22.));
+    assert_eq!(window_stop_stage.duration, window_stop);
+    assert_eq!(window_stop_stage.gas, ean_50);
+
+    // window stop time added to TTS, partially offset by off-gassing on deco gas during stop
+    let tts_delta = window_stop_runtime.tts - default_runtime.tts;
+    assert!(tts_delta > Time::zero());
+    assert!(tts_delta <= window_stop);
+}
+
+#[test]
+fn test_deco_severity() {
+    let air = Gas::air();
+    let severity = |depth: f64, bottom_minutes: f64| {
+        let mut model = fixtures::model_gf((30, 70));
+        model.record(
+            Depth::from_meters(depth),
+            Time::from_minutes(bottom_minutes),
+            &air,
+        );
+        model.deco(vec![air]).unwrap().severity()
+    };
+    // no deco
+    assert_eq!(severity(20., 10.), 0.);
+    // deep long dive
+    let deep_dive_severity = severity(60., 60.);
+    assert!(deep_dive_severity > 0.85 && deep_dive_severity < 1.);
+    // increasing with bottom time at fixed depth
+    let severities: Vec<f64> = (5..=60)
+        .step_by(5)
+        .map(|bottom_minutes| severity(40., bottom_minutes as f64))
+        .collect();
+    assert!(severities.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_deco_severity_with_safety_stop() {
+    let air = Gas::air();
+    let config = BuehlmannConfig::default()
+        .with_gradient_factors(30, 70)
+        .with_safety_stop(SafetyStop {
+            depth: Depth::from_meters(5.),
+            time: Time::from_minutes(3.),
+            required_above_depth: Depth::from_meters(10.),
+        });
+    let mut model = BuehlmannModel::new(config);
+    model.record(Depth::from_meters(20.), Time::from_minutes(10.), &air);
+    let runtime = model.deco(vec![air]).unwrap();
+    assert!(runtime
+        .deco_stages
+        .iter()
+        .any(|stage| stage.stage_type == DecoStageType::SafetyStop));
+    assert_eq!(runtime.severity(), 0.);
+}
+
+#[test]
+fn test_deco_iter_matches_deco_stages() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+
+    let gas_mixes = vec![air, ean_50, oxygen];
+    let deco_runtime = model.deco(gas_mixes.clone()).unwrap();
+    let deco_stages: Vec<DecoStage> = model
+        .deco_iter(gas_mixes.clone())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(deco_stages, deco_runtime.deco_stages);
+
+    // early stop, only the first stages calculated
+    let next_stages: Vec<DecoStage> = model
+        .deco_iter(gas_mixes)
+        .unwrap()
+        .take(2)
+        .map(|stage| stage.unwrap())
+        .collect();
+    assert_eq!(next_stages, deco_runtime.deco_stages[..2]);
+
+    assert!(model.deco_iter(vec![]).is_err());
+}
+
+#[test]
+fn test_stage_durations_sum() {
+    let air = Gas::air();
+    let ean_50 = Gas::new(0.5, 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+    let deco_runtime = model.deco(vec![air, ean_50]).unwrap();
+
+    let stage_durations: Vec<Time> = deco_runtime
+        .deco_stages
+        .iter()
+        .map(|stage| stage.duration)
+        .collect();
+    assert_eq!(stage_durations.iter().sum::<Time>(), deco_runtime.tts);
+    assert_eq!(Time::total(&stage_durations), deco_runtime.tts);
+}
+
+#[test]
+fn test_deco_stop_resolution() {
+    let air = Gas::air();
+    let deco_runtime = |deco_stop_resolution: Time| {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_deco_stop_resolution(deco_stop_resolution);
+        let mut model = CeilingCountingModel::new(config);
+        model.record(Depth::from_meters(40.), Time::from_minutes(30.), &air);
+        let deco_runtime = model.deco(vec![air]).unwrap();
+        (deco_runtime, model.ceiling_evaluations.get())
+    };
+
+    let (fine_runtime, fine_ceiling_evaluations) = deco_runtime(Time::from_seconds(1.));
+    let (coarse_runtime, coarse_ceiling_evaluations) = deco_runtime(Time::from_seconds(10.));
+    let tts_delta = (coarse_runtime.tts - fine_runtime.tts).as_seconds().abs();
+    assert!(tts_delta <= 10., "TTS delta [{}s]", tts_delta);
+    assert!(coarse_ceiling_evaluations * 5 < fine_ceiling_evaluations);
+}
+
+// Buehlmann model wrapper counting ceiling evaluations (counter shared with forks)
+#[derive(Clone, Debug)]
+struct CeilingCountingModel {
+    model: BuehlmannModel,
+    ceiling_evaluations: Rc<Cell<usize>>,
+}
+
+impl Sim for CeilingCountingModel {
+    fn fork(&self) -> Self {
+        Self {
+            model: self.model.fork(),
+            ceiling_evaluations: self.ceiling_evaluations.clone(),
+        }
+    }
+    fn is_sim(&self) -> bool {
+        self.model.is_sim()
+    }
+}
+
+impl DecoModel for CeilingCountingModel {
+    type ConfigType = BuehlmannConfig;
+
+    fn default() -> Self {
+        Self::new(BuehlmannConfig::default())
+    }
+    fn new(config: BuehlmannConfig) -> Self {
+        Self {
+            model: BuehlmannModel::new(config),
+            ceiling_evaluations: Rc::new(Cell::new(0)),
+        }
+    }
+    fn config(&self) -> BuehlmannConfig {
+        self.model.config()
+    }
+    fn dive_state(&self) -> DiveState {
+        self.model.dive_state()
+    }
+    fn record(&mut self, depth: Depth, time: Time, gas: &Gas) {
+        self.model.record(depth, time, gas);
+    }
+    fn record_travel(&mut self, target_depth: Depth, time: Time, gas: &Gas) {
+        self.model.record_travel(target_depth, time, gas);
+    }
+    fn record_travel_with_rate(&mut self, target_depth: Depth, rate: f64, gas: &Gas) {
+        self.model.record_travel_with_rate(target_depth, rate, gas);
+    }
+    fn ndl(&self) -> Time {
+        self.model.ndl()
+    }
+    fn ceiling(&self) -> Depth {
+        self.ceiling_evaluations
+            .set(self.ceiling_evaluations.get() + 1);
+        self.model.ceiling()
+    }
+    fn safe_to_surface(&self) -> bool {
+        self.model.safe_to_surface()
+    }
+    fn deco(&self, gas_mixes: Vec<Gas>) -> Result<DecoRuntime, DecoCalculationError> {
+        Deco::default().calc(self.fork(), gas_mixes)
+    }
+    fn cns(&self) -> f64 {
+        self.model.cns()
+    }
+    fn otu(&self) -> f64 {
+        self.model.otu()
+    }
+}
+
+#[test]
+fn test_explain_gas_selection() {
+    let air = Gas::air();
+    let ean50 = Gas::new(0.5, 0.);
+    let oxygen = Gas::new(1., 0.);
+    let mut model = fixtures::model_gf((30, 70));
+    model.record(Depth::from_meters(40.), Time::from_minutes(20.), &air);
+    model.record(Depth::from_meters(21.), Time::zero(), &air);
+
+    let reasons = model.explain_gas_selection(vec![air, ean50, oxygen]);
+    assert_eq!(
+        reasons,
+        vec![
+            GasSelectionReason {
+                gas: air,
+                outcome: GasSelectionOutcome::CurrentGas
+            },
+            GasSelectionReason {
+                gas: ean50,
+                outcome: GasSelectionOutcome::Selected
+            },
+            GasSelectionReason {
+                gas: oxygen,
+                outcome: GasSelectionOutcome::OverMod
+            },
+        ]
+    );
+    assert_eq!(reasons[2].outcome.to_string(), "over MOD");
+
+    // consistent with deco calculation switching to EAN50 at current depth
+    let runtime = model.deco(vec![air, ean50, oxygen]).unwrap();
+    let first_switch = runtime
+        .deco_stages
+        .iter()
+        .find(|stage| stage.stage_type == DecoStageType::GasSwitch)
+        .unwrap();
+    assert_eq!(first_switch.gas, ean50);
+    assert_eq!(first_switch.start_depth, Depth::from_meters(21.));
+
+    // leaner gas after switch
+    model.record(Depth::from_meters(21.), Time::zero(), &ean50);
+    let reasons = model.explain_gas_selection(vec![air, ean50]);
+    assert_eq!(reasons[0].outcome, GasSelectionOutcome::TooLean);
+}
+
 fn get_first_deco_stop_depth(deco: DecoRuntime) -> Option<Depth> {
     let first_stop = deco
         .deco_stages
@@ -1,4 +1,7 @@
-use dive_deco::{DecoModel, Depth, Gas, Supersaturation, Time};
+use dive_deco::{
+    BuehlmannConfig, BuehlmannModel, CeilingType, DecoModel, DecoStageType, Depth, Gas,
+    Supersaturation, Time,
+};
 pub mod fixtures;
 
 #[test]
@@ -36,3 +39,74 @@ fn test_heliox_gf_surf() {
 
     assert_close_to_percent!(gf_surf, 197.93, 1.);
 }
+
+#[test]
+fn test_tmx_ndl_reference_values() {
+    // reference NDLs (GF 100/100) calculated independently with ZH-L16C N2 / ZH-L16A He
+    // Haldane equation and He / N2 weighted M-value coefficients
+    let cases = vec![
+        ((0.21, 0.35), [(20., 37.), (30., 14.), (40., 7.), (50., 4.)]),
+        ((0.18, 0.45), [(20., 29.), (30., 11.), (40., 6.), (50., 4.)]),
+        (
+            (0.30, 0.30),
+            [(20., 61.), (30., 20.), (40., 10.), (50., 6.)],
+        ),
+        ((0.21, 0.79), [(20., 19.), (30., 8.), (40., 4.), (50., 3.)]),
+    ];
+    for ((o2, he), reference_ndls) in cases {
+        let gas = Gas::new(o2, he);
+        for (depth, reference_ndl) in reference_ndls {
+            let mut model = fixtures::model_gf((100, 100));
+            model.record(Depth::from_meters(depth), Time::zero(), &gas);
+            assert_eq!(
+                model.ndl(),
+                Time::from_minutes(reference_ndl),
+                "{}/{} at {}m",
+                o2 * 100.,
+                he * 100.,
+                depth
+            );
+        }
+    }
+}
+
+#[test]
+fn test_tmx_adaptive_ndl() {
+    // off-gassing during ascent (incl. faster He washout) extends NDL
+    let tmx = Gas::new(0.21, 0.35);
+    let mut actual_model = fixtures::model_gf((100, 100));
+    let mut adaptive_model = BuehlmannModel::new(
+        BuehlmannConfig::default()
+            .with_gradient_factors(100, 100)
+            .with_ceiling_type(CeilingType::Adaptive),
+    );
+    actual_model.record(Depth::from_meters(30.), Time::zero(), &tmx);
+    adaptive_model.record(Depth::from_meters(30.), Time::zero(), &tmx);
+    assert_eq!(actual_model.ndl(), Time::from_minutes(14.));
+    assert!(adaptive_model.ndl() > actual_model.ndl());
+}
+
+#[test]
+fn test_prefer_he_washout() {
+    let tmx_18_45 = Gas::new(0.18, 0.45);
+    let tmx_31_20 = Gas::new(0.31, 0.2);
+    let ean_32 = Gas::new(0.32, 0.);
+    let first_switch_gas = |prefer_he_washout: bool| {
+        let config = BuehlmannConfig::new()
+            .with_gradient_factors(30, 70)
+            .with_prefer_he_washout(prefer_he_washout);
+        let mut model = BuehlmannModel::new(config);
+        model.record(Depth::from_meters(50.), Time::from_minutes(25.), &tmx_18_45);
+        model
+            .deco(vec![tmx_18_45, tmx_31_20, ean_32])
+            .unwrap()
+            .deco_stages
+            .into_iter()
+            .find(|stage| stage.stage_type == DecoStageType::GasSwitch)
+            .unwrap()
+            .gas
+    };
+
+    assert_eq!(first_switch_gas(false), tmx_31_20);
+    assert_eq!(first_switch_gas(true), ean_32);
+}
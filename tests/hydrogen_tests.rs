@@ -0,0 +1,39 @@
+use dive_deco::{Compartment, DecoModel, Depth, Gas, Time};
+pub mod fixtures;
+
+#[test]
+fn test_hydrox_fast_compartments_ongassing() {
+    let mut hydrox_model = fixtures::model_default();
+    let mut heliox_model = fixtures::model_default();
+    let hydrox = Gas::new_with_h2(0.04, 0., 0.96);
+    let heliox = Gas::new(0.04, 0.96);
+
+    hydrox_model.record(Depth::from_meters(100.), Time::from_minutes(2.), &hydrox);
+    heliox_model.record(Depth::from_meters(100.), Time::from_minutes(2.), &heliox);
+
+    let hydrox_tissues = hydrox_model.tissues();
+    let heliox_tissues = heliox_model.tissues();
+    for i in 0..4 {
+        let Compartment {
+            h2_ip, total_ip, ..
+        } = hydrox_tissues[i];
+        assert!(h2_ip > 0.);
+        assert!(
+            total_ip > heliox_tissues[i].total_ip,
+            "compartment {} hydrogen loading should exceed helium loading",
+            i + 1
+        );
+    }
+}
+
+#[test]
+fn test_hydreliox_ceiling() {
+    let mut model = fixtures::model_gf((30, 70));
+    let hydreliox = Gas::new_with_h2(0.04, 0.5, 0.3);
+    model.record(
+        Depth::from_meters(100.),
+        Time::from_minutes(20.),
+        &hydreliox,
+    );
+    assert!(model.ceiling() > Depth::zero());
+}
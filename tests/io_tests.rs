@@ -0,0 +1,93 @@
+#![cfg(feature = "io")]
+
+use dive_deco::{
+    io::{parse_profile_csv, ParseError},
+    DecoModel, Depth, Gas, ProfileSegment, Time,
+};
+
+pub mod fixtures;
+
+#[test]
+fn test_parse_profile_csv() {
+    let csv = "time_s,depth_m,gas
+60,20,air
+1260,20,air
+
+1380,6,EAN50
+1560,6,EAN50
+";
+    let segments = parse_profile_csv(csv).unwrap();
+    let ean_50 = Gas::new(0.5, 0.);
+    assert_eq!(
+        segments,
+        vec![
+            ProfileSegment {
+                depth: Depth::from_meters(20.),
+                time: Time::from_minutes(1.),
+                gas: Gas::air(),
+            },
+            ProfileSegment {
+                depth: Depth::from_meters(20.),
+                time: Time::from_minutes(20.),
+                gas: Gas::air(),
+            },
+            ProfileSegment {
+                depth: Depth::from_meters(6.),
+                time: Time::from_minutes(2.),
+                gas: ean_50,
+            },
+            ProfileSegment {
+                depth: Depth::from_meters(6.),
+                time: Time::from_minutes(3.),
+                gas: ean_50,
+            },
+        ]
+    );
+
+    // replayed profile
+    let mut model = fixtures::model_default();
+    model.record_profile(&segments).unwrap();
+    let state = model.dive_state();
+    assert_eq!(state.depth, Depth::from_meters(6.));
+    assert_eq!(state.time, Time::from_minutes(26.));
+    assert_eq!(state.gas, ean_50);
+}
+
+#[test]
+fn test_parse_profile_csv_invalid_row() {
+    let invalid_rows = vec![
+        (
+            "60,20,air\n120,abc,air",
+            ParseError {
+                line: 2,
+                reason: "Invalid depth value [abc]".to_string(),
+            },
+        ),
+        (
+            "60,20",
+            ParseError {
+                line: 1,
+                reason: "Expected 3 fields (time_s,depth_m,gas), found 2".to_string(),
+            },
+        ),
+        (
+            "time_s,depth_m,gas\n60,20,air\n30,20,air",
+            ParseError {
+                line: 3,
+                reason: "Time [30s] earlier than previous sample [60s]".to_string(),
+            },
+        ),
+        (
+            "60,20,trimix",
+            ParseError {
+                line: 1,
+                reason: "Invalid gas [trimix]".to_string(),
+            },
+        ),
+    ];
+    for (csv, expected_err) in invalid_rows {
+        let err = parse_profile_csv(csv).unwrap_err();
+        assert_eq!(err, expected_err);
+        assert!(err.to_string().starts_with(&format!("Line {}:", err.line)));
+    }
+}